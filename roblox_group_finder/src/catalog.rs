@@ -0,0 +1,32 @@
+use std::sync::Mutex;
+
+use fxhash::FxHashSet;
+use roblox_api::apis::Id;
+
+// Groups that were already ownerless the first time they were scanned
+#[derive(Debug, Default)]
+pub struct OwnerlessCatalog {
+    groups: Mutex<FxHashSet<Id>>,
+}
+
+impl OwnerlessCatalog {
+    pub fn insert(&self, id: Id) {
+        self.groups.lock().unwrap().insert(id);
+    }
+
+    pub fn remove(&self, id: Id) {
+        self.groups.lock().unwrap().remove(&id);
+    }
+
+    pub fn contains(&self, id: Id) -> bool {
+        self.groups.lock().unwrap().contains(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.groups.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.lock().unwrap().is_empty()
+    }
+}
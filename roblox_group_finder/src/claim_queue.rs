@@ -0,0 +1,359 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BTreeSet,
+    fs::{self, File, OpenOptions},
+    io::{self, ErrorKind, Write},
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use fxhash::FxHashSet;
+use roblox_api::apis::Id;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tracing::warn;
+
+use crate::threads::ClaimCandidate;
+
+// Bigger groups first, then older ones, then lower ids. Groups of unknown age rank below
+// known ones of the same size.
+type Value = (u64, Option<Reverse<SystemTime>>, Reverse<Id>);
+
+fn estimated_value(candidate: &ClaimCandidate) -> Value {
+    (
+        candidate.details.member_count,
+        candidate.details.created.map(Reverse),
+        Reverse(candidate.details.id),
+    )
+}
+
+#[derive(Debug)]
+struct Queued(ClaimCandidate);
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl Eq for Queued {}
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        estimated_value(&self.0).cmp(&estimated_value(&other.0))
+    }
+}
+
+#[derive(Serialize)]
+struct OverflowEntry<'a> {
+    group_id: Id,
+    member_count: u64,
+    name: &'a str,
+    recorded_at: u64,
+}
+
+impl<'a> OverflowEntry<'a> {
+    fn new(candidate: &'a ClaimCandidate) -> Self {
+        let details = &candidate.details;
+        Self {
+            group_id: details.id,
+            member_count: details.member_count,
+            name: &details.name,
+            recorded_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct LoggedGroup {
+    group_id: Id,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    queued: BTreeSet<Queued>,
+    overflow: BTreeSet<Queued>,
+    // Groups that are queued, overflowed or being claimed
+    ids: FxHashSet<Id>,
+}
+
+/// Candidates waiting to be claimed, most valuable first.
+///
+/// Only `capacity` candidates are queued at once. Less valuable ones wait in an overflow list
+/// and move up as the queue drains. If an overflow log is set, each overflowed candidate is also
+/// appended to it, and [`ClaimQueue::save_pending`] rewrites it with every candidate still
+/// waiting. Those groups are already ownerless, so a restarted finder would ignore them unless
+/// they are read back with [`read_overflow_log`] and checked again.
+#[derive(Debug)]
+pub struct ClaimQueue {
+    state: Mutex<QueueState>,
+    available: Notify,
+    capacity: usize,
+    overflow_log: Option<Mutex<File>>,
+}
+
+impl ClaimQueue {
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(QueueState::default()),
+            available: Notify::new(),
+            capacity: capacity.max(1),
+            overflow_log: None,
+        }
+    }
+
+    pub fn with_overflow_log(capacity: usize, path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open claim overflow log at {}", path.display()))?;
+        Ok(Self {
+            overflow_log: Some(Mutex::new(file)),
+            ..Self::new(capacity)
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().queued.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state.lock().unwrap().queued.is_empty()
+    }
+
+    pub fn overflow_len(&self) -> usize {
+        self.state.lock().unwrap().overflow.len()
+    }
+
+    /// Queues a candidate, returning `false` if the group is already queued or being claimed.
+    pub fn push(&self, candidate: ClaimCandidate) -> bool {
+        let overflowed = {
+            let state = &mut *self.state.lock().unwrap();
+            if !state.ids.insert(candidate.group.id) {
+                return false;
+            }
+            state.queued.insert(Queued(candidate));
+            if state.queued.len() > self.capacity {
+                let lowest = state.queued.pop_first().unwrap();
+                let entry = self
+                    .overflow_log
+                    .as_ref()
+                    .map(|_| serde_json::to_vec(&OverflowEntry::new(&lowest.0)));
+                state.overflow.insert(lowest);
+                entry
+            } else {
+                None
+            }
+        };
+        if let Some(entry) = overflowed {
+            if let Err(error) = self.log_overflow(entry.map_err(io::Error::from)) {
+                warn!("Failed to log claim queue overflow, error: {}", error);
+            }
+        }
+        self.available.notify_one();
+        true
+    }
+
+    fn log_overflow(&self, entry: io::Result<Vec<u8>>) -> io::Result<()> {
+        let Some(file) = &self.overflow_log else {
+            return Ok(());
+        };
+        let mut line = entry?;
+        line.push(b'\n');
+        file.lock().unwrap().write_all(&line)
+    }
+
+    pub fn try_pop(&self) -> Option<ClaimCandidate> {
+        let state = &mut *self.state.lock().unwrap();
+        let best = state.queued.pop_last()?;
+        if let Some(next) = state.overflow.pop_last() {
+            state.queued.insert(next);
+        }
+        Some(best.0)
+    }
+
+    /// Waits for the most valuable candidate. Dropping the future never loses one.
+    pub async fn pop(&self) -> ClaimCandidate {
+        loop {
+            let available = self.available.notified();
+            if let Some(candidate) = self.try_pop() {
+                return candidate;
+            }
+            available.await;
+        }
+    }
+
+    /// Puts back a candidate that was popped but not claimed.
+    pub fn requeue(&self, candidate: ClaimCandidate) {
+        self.finish(candidate.group.id);
+        self.push(candidate);
+    }
+
+    /// Lets a group be queued again once its claim attempt is over.
+    pub fn finish(&self, id: Id) {
+        self.state.lock().unwrap().ids.remove(&id);
+    }
+
+    /// Replaces the overflow log with the candidates that are still queued or overflowed, so
+    /// that claimed and stale entries don't pile up across runs.
+    pub fn save_pending(&self) -> Result<()> {
+        let Some(file) = &self.overflow_log else {
+            return Ok(());
+        };
+        let mut contents = Vec::new();
+        {
+            let state = &*self.state.lock().unwrap();
+            for queued in state.queued.iter().rev().chain(state.overflow.iter().rev()) {
+                serde_json::to_writer(&mut contents, &OverflowEntry::new(&queued.0))?;
+                contents.push(b'\n');
+            }
+        }
+        let file = &mut *file.lock().unwrap();
+        // The file is in append mode, so writes land at the new end
+        file.set_len(0)?;
+        file.write_all(&contents)
+            .with_context(|| "Failed to rewrite the claim overflow log")
+    }
+}
+
+/// The distinct groups in an overflow log, in the order they were first logged. A missing log
+/// has none.
+pub fn read_overflow_log(path: &Path) -> Result<Vec<Id>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => {
+            return Err(error).with_context(|| {
+                format!("Failed to read claim overflow log at {}", path.display())
+            })
+        }
+    };
+    let mut seen = FxHashSet::default();
+    Ok(contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(index, line)| {
+            serde_json::from_str::<LoggedGroup>(line)
+                .inspect_err(|error| {
+                    warn!(
+                        "Skipping claim overflow log line {}, error: {}",
+                        index + 1,
+                        error
+                    );
+                })
+                .ok()
+        })
+        .map(|logged| logged.group_id)
+        .filter(|id| seen.insert(*id))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, io::Write};
+
+    use tokio::time::Instant;
+
+    use super::{read_overflow_log, ClaimQueue};
+    use crate::{
+        fake::{group_info, id},
+        latency::DetectionTimes,
+        threads::{ClaimCandidate, TrackedGroup},
+    };
+
+    fn candidate(group_id: u64, member_count: u64) -> ClaimCandidate {
+        ClaimCandidate {
+            group: TrackedGroup {
+                id: id(group_id),
+                ..TrackedGroup::default()
+            },
+            details: group_info(group_id, "", member_count),
+            times: DetectionTimes {
+                observed: Instant::now(),
+                detailed: Instant::now(),
+            },
+        }
+    }
+
+    fn pop_all(queue: &ClaimQueue) -> Vec<u64> {
+        std::iter::from_fn(|| queue.try_pop())
+            .map(|candidate| candidate.group.id.get())
+            .collect()
+    }
+
+    #[test]
+    fn pops_the_most_valuable_group_first_and_drops_duplicates() {
+        let queue = ClaimQueue::new(10);
+        for (group_id, member_count) in [(1, 5), (2, 50), (3, 5), (2, 50), (4, 0)] {
+            queue.push(candidate(group_id, member_count));
+        }
+        assert_eq!(queue.len(), 4);
+        let best = queue.try_pop().unwrap();
+        assert_eq!(best.group.id, id(2));
+        // Still being claimed, so not queued again
+        assert!(!queue.push(candidate(2, 50)));
+        queue.finish(id(2));
+        assert!(queue.push(candidate(2, 50)));
+        let best = queue.try_pop().unwrap();
+        queue.requeue(best);
+        assert_eq!(pop_all(&queue), [2, 1, 3, 4]);
+    }
+
+    #[test]
+    fn overflow_is_logged_and_drains_back_into_the_queue() {
+        let path = env::temp_dir().join(format!("claim_overflow_{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let queue = ClaimQueue::with_overflow_log(2, &path).unwrap();
+        for (group_id, member_count) in [(1, 10), (2, 30), (3, 20), (4, 0)] {
+            queue.push(candidate(group_id, member_count));
+        }
+        assert_eq!((queue.len(), queue.overflow_len()), (2, 2));
+        let log = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let logged: Vec<u64> = log
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["group_id"]
+                    .as_u64()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(logged, [1, 4]);
+        assert_eq!(pop_all(&queue), [2, 3, 1, 4]);
+    }
+
+    #[test]
+    fn pending_candidates_replace_the_overflow_log() {
+        let path = env::temp_dir().join(format!("claim_pending_{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let queue = ClaimQueue::with_overflow_log(1, &path).unwrap();
+        for (group_id, member_count) in [(1, 10), (2, 30), (3, 20)] {
+            queue.push(candidate(group_id, member_count));
+        }
+        // A group logged again by an earlier run is read back once, and a torn line is skipped
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"group_id\":1}\n{\"group_")
+            .unwrap();
+        assert_eq!(read_overflow_log(&path).unwrap(), [id(1), id(3)]);
+
+        queue.try_pop().unwrap();
+        queue.save_pending().unwrap();
+        let pending = read_overflow_log(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(pending, [id(3), id(1)]);
+        assert!(read_overflow_log(&path).unwrap().is_empty());
+    }
+}
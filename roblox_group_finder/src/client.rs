@@ -0,0 +1,301 @@
+use std::{
+    fmt::{self, Debug},
+    time::SystemTime,
+};
+
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use roblox_api::{
+    apis::{
+        economy::EconomyAuthenticatedApi,
+        groups::{GroupsApi, GroupsAuthenticatedApi, Metadata},
+        users::UsersAuthenticatedApi,
+        Error, Id,
+    },
+    AuthenticatedClient, BaseClient,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    config::Settings,
+    constants::{
+        ALREADY_OWNED_MESSAGE, CAPTCHA_MESSAGE, GROUPS_BATCH_API_URL, INVALID_GROUP_MESSAGE,
+        RATE_LIMITED_MESSAGE, USERS_GROUPS_API_URL,
+    },
+    utils,
+};
+
+// The subset of the Roblox API the finder uses, so that workers can run against a fake in tests
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiFailure {
+    RateLimited,
+    ChallengeRequired,
+    InvalidGroup,
+    AlreadyOwned,
+    Api(String),
+    Other(String),
+}
+impl From<Error> for ApiFailure {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Api(error) => match error.message.as_str() {
+                RATE_LIMITED_MESSAGE => Self::RateLimited,
+                CAPTCHA_MESSAGE => Self::ChallengeRequired,
+                INVALID_GROUP_MESSAGE => Self::InvalidGroup,
+                ALREADY_OWNED_MESSAGE => Self::AlreadyOwned,
+                _ => Self::Api(error.message),
+            },
+            error => Self::Other(format!("{error:?}")),
+        }
+    }
+}
+
+impl fmt::Display for ApiFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RateLimited => f.write_str(RATE_LIMITED_MESSAGE),
+            Self::ChallengeRequired => f.write_str(CAPTCHA_MESSAGE),
+            Self::InvalidGroup => f.write_str(INVALID_GROUP_MESSAGE),
+            Self::AlreadyOwned => f.write_str(ALREADY_OWNED_MESSAGE),
+            Self::Api(message) | Self::Other(message) => f.write_str(message),
+        }
+    }
+}
+impl std::error::Error for ApiFailure {}
+
+impl From<reqwest::Error> for ApiFailure {
+    fn from(error: reqwest::Error) -> Self {
+        if error.status() == Some(StatusCode::TOO_MANY_REQUESTS) {
+            Self::RateLimited
+        } else {
+            Self::Other(format!("{error:?}"))
+        }
+    }
+}
+
+pub type ApiResult<T> = Result<T, ApiFailure>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchGroupInfo {
+    pub id: Id,
+    pub owner: Option<Id>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetailedGroupInfo {
+    pub id: Id,
+    pub owner: Option<Id>,
+    pub public_entry_allowed: bool,
+    pub is_locked: bool,
+    pub name: String,
+    pub description: String,
+    pub member_count: u64,
+    // Only the v2 groups endpoint reports this, so the detailed check fills it in before claiming
+    pub created: Option<SystemTime>,
+}
+
+#[async_trait]
+pub trait CheckClient: Send + Sync {
+    async fn batch_info(&self, ids: &[Id]) -> ApiResult<Vec<BatchGroupInfo>>;
+
+    async fn detailed_info(&self, id: Id) -> ApiResult<DetailedGroupInfo>;
+}
+
+#[async_trait]
+impl<T: BaseClient + Send + Sync> CheckClient for T {
+    async fn batch_info(&self, ids: &[Id]) -> ApiResult<Vec<BatchGroupInfo>> {
+        Ok(GroupsApi::get_batch_info(self, ids.iter().copied())
+            .await?
+            .into_iter()
+            .map(|group_info| BatchGroupInfo {
+                id: group_info.id,
+                owner: group_info.owner.map(|owner| owner.id),
+            })
+            .collect())
+    }
+
+    async fn detailed_info(&self, id: Id) -> ApiResult<DetailedGroupInfo> {
+        let group_info = GroupsApi::get_detailed_info(self, id).await?;
+        Ok(DetailedGroupInfo {
+            id: group_info.id,
+            owner: group_info.owner.map(|owner| owner.user_id),
+            public_entry_allowed: group_info.public_entry_allowed,
+            is_locked: group_info.is_locked.unwrap_or_default(),
+            name: group_info.name,
+            description: group_info.description,
+            member_count: group_info.member_count,
+            // The group info endpoint doesn't report when a group was created
+            created: None,
+        })
+    }
+}
+
+#[async_trait]
+pub trait ClaimClient: Send + Sync {
+    async fn join(&self, id: Id) -> ApiResult<()>;
+
+    async fn claim(&self, id: Id) -> ApiResult<()>;
+
+    /// The group's current owner, checked after another account claims it first.
+    async fn owner(&self, id: Id) -> ApiResult<Option<Id>>;
+
+    async fn funds(&self, id: Id) -> ApiResult<u64>;
+
+    async fn leave(&self, id: Id, user_id: Id) -> ApiResult<()>;
+
+    async fn metadata(&self) -> ApiResult<Metadata>;
+
+    async fn user_id(&self) -> ApiResult<Id>;
+}
+
+// Endpoints roblox_api has no wrapper for. None of them need authentication, so the real
+// implementation is a plain HTTP client built by `http_client`
+#[async_trait]
+pub trait WebClient: Debug + Send + Sync {
+    /// Every group the user is a member of, including the ones they own.
+    async fn memberships(&self, user_id: Id) -> ApiResult<Vec<DetailedGroupInfo>>;
+
+    /// When the group was created, or `None` if it doesn't exist.
+    async fn created(&self, id: Id) -> ApiResult<Option<SystemTime>>;
+}
+
+/// The client for endpoints `roblox_api` has no wrapper for, with the configured timeouts.
+///
+/// Build it once and share it, so requests reuse its connections.
+#[must_use]
+pub fn http_client(settings: &Settings) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(settings.timeout)
+        .connect_timeout(settings.connect_timeout)
+        .build()
+        .expect("Failed to build the HTTP client")
+}
+
+#[derive(Deserialize)]
+struct Memberships {
+    data: Vec<Membership>,
+}
+
+#[derive(Deserialize)]
+struct Membership {
+    group: MembershipGroup,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MembershipGroup {
+    id: Id,
+    name: String,
+    #[serde(default)]
+    description: String,
+    owner: Option<MembershipOwner>,
+    member_count: u64,
+    #[serde(default)]
+    public_entry_allowed: bool,
+    #[serde(default)]
+    is_locked: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MembershipOwner {
+    user_id: Id,
+}
+
+#[derive(Deserialize)]
+struct Groups {
+    data: Vec<GroupCreated>,
+}
+
+#[derive(Deserialize)]
+struct GroupCreated {
+    created: String,
+}
+
+#[async_trait]
+impl<T: AuthenticatedClient + Send + Sync> ClaimClient for T {
+    async fn join(&self, id: Id) -> ApiResult<()> {
+        GroupsAuthenticatedApi::join_group(self, id, None).await?;
+        Ok(())
+    }
+
+    async fn claim(&self, id: Id) -> ApiResult<()> {
+        GroupsAuthenticatedApi::claim_group(self, id).await?;
+        Ok(())
+    }
+
+    async fn owner(&self, id: Id) -> ApiResult<Option<Id>> {
+        Ok(CheckClient::detailed_info(self, id).await?.owner)
+    }
+
+    async fn funds(&self, id: Id) -> ApiResult<u64> {
+        Ok(EconomyAuthenticatedApi::get_group_funds(self, id).await?)
+    }
+
+    async fn leave(&self, id: Id, user_id: Id) -> ApiResult<()> {
+        GroupsAuthenticatedApi::remove_user_from_group(self, id, user_id).await?;
+        Ok(())
+    }
+
+    async fn metadata(&self) -> ApiResult<Metadata> {
+        Ok(GroupsApi::get_metadata(self).await?)
+    }
+
+    async fn user_id(&self) -> ApiResult<Id> {
+        Ok(UsersAuthenticatedApi::get_authenticated_user(self)
+            .await?
+            .id)
+    }
+}
+
+#[async_trait]
+impl WebClient for reqwest::Client {
+    async fn memberships(&self, user_id: Id) -> ApiResult<Vec<DetailedGroupInfo>> {
+        let memberships: Memberships = self
+            .get(format!("{USERS_GROUPS_API_URL}/{user_id}/groups/roles"))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(memberships
+            .data
+            .into_iter()
+            .map(|membership| {
+                let group = membership.group;
+                DetailedGroupInfo {
+                    id: group.id,
+                    owner: group.owner.map(|owner| owner.user_id),
+                    public_entry_allowed: group.public_entry_allowed,
+                    is_locked: group.is_locked,
+                    name: group.name,
+                    description: group.description,
+                    member_count: group.member_count,
+                    created: None,
+                }
+            })
+            .collect())
+    }
+
+    async fn created(&self, id: Id) -> ApiResult<Option<SystemTime>> {
+        let groups: Groups = self
+            .get(GROUPS_BATCH_API_URL)
+            .query(&[("groupIds", id)])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        groups
+            .data
+            .first()
+            .map(|group| {
+                utils::parse_timestamp(&group.created).ok_or_else(|| {
+                    ApiFailure::Other(format!("Invalid creation date {}", group.created))
+                })
+            })
+            .transpose()
+    }
+}
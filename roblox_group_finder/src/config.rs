@@ -1,10 +1,11 @@
 use anyhow::{ensure, Context, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use figment::{
     providers::{Format, Serialized, Toml},
     Figment,
 };
 use itertools::Itertools;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::{
     fs,
@@ -13,10 +14,17 @@ use std::{
 };
 use tracing::warn;
 
+use crate::{
+    hook::HookConfig,
+    policy::{Rule, RuleAction},
+    webhook::WebhookConfig,
+};
+
 use self::defaults::{
-    DEFAULT_BATCH_WAIT, DEFAULT_CONFIG_PATH, DEFAULT_CONNECT_TIMEOUT, DEFAULT_DETAILED_WAIT,
-    DEFAULT_FUNDS_THRESHOLD, DEFAULT_HTTP_PATH, DEFAULT_RETRY_LIMIT, DEFAULT_SOCKS5_PATH,
-    DEFAULT_TIMEOUT,
+    DEFAULT_AUTO_PRUNE, DEFAULT_BATCH_WAIT, DEFAULT_CLAIM_OVERFLOW_LOG_PATH, DEFAULT_CONFIG_PATH,
+    DEFAULT_CONNECT_TIMEOUT, DEFAULT_DETAILED_WAIT, DEFAULT_FUNDS_THRESHOLD, DEFAULT_HTTP_PATH,
+    DEFAULT_LATEST_MAX_GAP, DEFAULT_LEDGER_PATH, DEFAULT_MAX_RECHECK, DEFAULT_MIN_RECHECK,
+    DEFAULT_OWNERLESS_POLICY, DEFAULT_RETRY_LIMIT, DEFAULT_SOCKS5_PATH, DEFAULT_TIMEOUT,
 };
 
 mod defaults;
@@ -25,6 +33,44 @@ mod defaults;
 pub struct Config {
     pub settings: Settings,
     pub proxies: String,
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum IdKind {
+    Group,
+    User,
+    // Places are assets too, so their ids are spread out across the asset ids
+    Place,
+    Asset,
+    Badge,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// Find the newest id of a kind of object and exit
+    #[command(name = "latest_id")]
+    LatestId {
+        #[arg(long = "kind", value_enum, default_value_t = IdKind::Group)]
+        kind: IdKind,
+
+        #[arg(long = "hint")]
+        hint: Option<u64>,
+    },
+    /// List the groups the account is in, with their funds and member counts, and exit
+    #[command(name = "portfolio")]
+    Portfolio,
+}
+
+// What to do with groups that are already ownerless the first time they are scanned
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum OwnerlessPolicy {
+    Ignore,
+    Recheck,
+    DetailedOnce,
 }
 
 #[derive(Debug)]
@@ -38,12 +84,70 @@ pub struct Settings {
     pub connect_timeout: Duration,
     pub batch_wait: Duration,
     pub detailed_wait: Duration,
+    pub min_recheck: Duration,
+    pub max_recheck: Duration,
+    pub ownerless_policy: OwnerlessPolicy,
+    pub latest_hint: Option<u64>,
+    pub latest_max_gap: usize,
+    pub rules: Vec<Rule>,
+    pub script: Option<PathBuf>,
+    pub webhooks: Vec<WebhookConfig>,
+    pub post_claim_hook: Option<HookConfig>,
+    pub auto_prune: bool,
+    // Groups with more funds than this are never pruned
+    pub prune_max_funds: u64,
+    pub ledger_path: Option<PathBuf>,
+    // Candidates that overflowed the claim queue, rewritten with the ones still waiting on exit.
+    // They are checked again on the next start, since unseen ownerless groups are otherwise left
+    // to the ownerless policy.
+    pub claim_overflow_log_path: Option<PathBuf>,
+}
+
+// A `[[rules]]` entry, evaluated in order by the claim policy
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct RuleConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_members: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_members: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_funds: Option<u64>,
+    pub action: RuleAction,
+}
+
+impl RuleConfig {
+    pub fn compile(self) -> Result<Rule> {
+        // Funds are only known after claiming, so they can't decide whether to claim
+        ensure!(
+            self.min_funds.is_none() || matches!(self.action, RuleAction::Keep | RuleAction::Leave),
+            "Rules with min_funds must use the keep or leave action"
+        );
+        let compile = |pattern: Option<String>| pattern.as_deref().map(Regex::new).transpose();
+        Ok(Rule {
+            min_members: self.min_members,
+            max_members: self.max_members,
+            name: compile(self.name).with_context(|| "Invalid name pattern")?,
+            description: compile(self.description)
+                .with_context(|| "Invalid description pattern")?,
+            min_funds: self.min_funds,
+            action: self.action,
+        })
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Parser)]
 #[command(name = "roblox_group_finder")]
 #[command(version, about, long_about = None)]
 struct Args {
+    #[serde(skip)]
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     #[arg(long = "retry")]
     retry_limit: Option<usize>,
@@ -93,10 +197,65 @@ struct Args {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[arg(long = "detailed_wait")]
     detailed_wait: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "min_recheck")]
+    min_recheck: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "max_recheck")]
+    max_recheck: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "ownerless_policy")]
+    ownerless_policy: Option<OwnerlessPolicy>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "latest_hint")]
+    latest_hint: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "latest_max_gap")]
+    latest_max_gap: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[arg(skip)]
+    rules: Vec<RuleConfig>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "script")]
+    script: Option<PathBuf>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[arg(skip)]
+    webhooks: Vec<WebhookConfig>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(skip)]
+    post_claim_hook: Option<HookConfig>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "auto_prune")]
+    auto_prune: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "prune_max_funds")]
+    prune_max_funds: Option<u64>,
+
+    #[serde(rename = "ledger")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "ledger")]
+    ledger_path: Option<PathBuf>,
+
+    #[serde(rename = "claim_overflow_log")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "claim_overflow_log")]
+    claim_overflow_log_path: Option<PathBuf>,
 }
 
 fn parse_args() -> Result<Args> {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    let command = args.command.take();
     let figment = Figment::new();
     if let Ok(config) = fs::read_to_string(&args.config_path) {
         figment.join(Toml::string(&config))
@@ -105,6 +264,7 @@ fn parse_args() -> Result<Args> {
         figment
     }
     .join(Serialized::defaults(Args {
+        command: None,
         http_path: Some(DEFAULT_HTTP_PATH.into()),
         socks5_path: Some(DEFAULT_SOCKS5_PATH.into()),
         retry_limit: Some(DEFAULT_RETRY_LIMIT),
@@ -117,8 +277,23 @@ fn parse_args() -> Result<Args> {
         connect_timeout: Some(DEFAULT_CONNECT_TIMEOUT),
         batch_wait: Some(DEFAULT_BATCH_WAIT),
         detailed_wait: Some(DEFAULT_DETAILED_WAIT),
+        min_recheck: Some(DEFAULT_MIN_RECHECK),
+        max_recheck: Some(DEFAULT_MAX_RECHECK),
+        ownerless_policy: Some(DEFAULT_OWNERLESS_POLICY),
+        latest_hint: None,
+        latest_max_gap: Some(DEFAULT_LATEST_MAX_GAP),
+        rules: Vec::new(),
+        script: None,
+        webhooks: Vec::new(),
+        post_claim_hook: None,
+        auto_prune: Some(DEFAULT_AUTO_PRUNE),
+        // Falls back to the funds threshold
+        prune_max_funds: None,
+        ledger_path: Some(DEFAULT_LEDGER_PATH.into()),
+        claim_overflow_log_path: Some(DEFAULT_CLAIM_OVERFLOW_LOG_PATH.into()),
     }))
     .extract::<Args>()
+    .map(|args| Args { command, ..args })
     .with_context(|| "Failed to merge CLI args and config files")
 }
 
@@ -134,8 +309,38 @@ fn try_read_file(path: &Path, file_content: &str) -> String {
 
 pub fn get_config() -> Result<Config> {
     let args = parse_args()?;
-    ensure!(args.browser_id.is_some(), "No browser ID provided");
-    ensure!(args.cookie.is_some(), "No group claimer account provided");
+    match args.command {
+        None => {
+            ensure!(args.browser_id.is_some(), "No browser ID provided");
+            ensure!(args.cookie.is_some(), "No group claimer account provided");
+        }
+        Some(Command::Portfolio) => {
+            ensure!(args.cookie.is_some(), "No group claimer account provided");
+        }
+        Some(Command::LatestId { .. }) => {}
+    }
+    ensure!(
+        args.min_recheck <= args.max_recheck,
+        "Minimum recheck interval is higher than maximum recheck interval"
+    );
+    ensure!(
+        cfg!(feature = "scripting") || args.script.is_none(),
+        "Claim scripts need the scripting feature"
+    );
+    for webhook in &args.webhooks {
+        reqwest::Url::parse(&webhook.url)
+            .with_context(|| format!("Invalid webhook URL {}", webhook.url))?;
+    }
+
+    let rules = args
+        .rules
+        .into_iter()
+        .enumerate()
+        .map(|(index, rule)| {
+            rule.compile()
+                .with_context(|| format!("Failed to load rule {}", index + 1))
+        })
+        .collect::<Result<_>>()?;
 
     let http_proxies = try_read_file(&args.http_path.unwrap(), "http proxies")
         .lines()
@@ -154,15 +359,34 @@ pub fn get_config() -> Result<Config> {
     Ok(Config {
         settings: Settings {
             retry_limit: args.retry_limit.unwrap(),
-            browser_id: args.browser_id.unwrap(),
+            browser_id: args.browser_id.unwrap_or_default(),
             funds_threshold: args.funds_threshold.unwrap(),
-            cookie: args.cookie.unwrap(),
+            cookie: args.cookie.unwrap_or_default(),
             user_agent: args.user_agent.unwrap_or_default(),
             timeout: Duration::from_millis(args.timeout.unwrap()),
             connect_timeout: Duration::from_millis(args.connect_timeout.unwrap()),
             batch_wait: Duration::from_millis(args.batch_wait.unwrap()),
             detailed_wait: Duration::from_millis(args.detailed_wait.unwrap()),
+            min_recheck: Duration::from_millis(args.min_recheck.unwrap()),
+            max_recheck: Duration::from_millis(args.max_recheck.unwrap()),
+            ownerless_policy: args.ownerless_policy.unwrap(),
+            latest_hint: args.latest_hint,
+            latest_max_gap: args.latest_max_gap.unwrap(),
+            rules,
+            script: args.script,
+            webhooks: args.webhooks,
+            post_claim_hook: args.post_claim_hook,
+            auto_prune: args.auto_prune.unwrap(),
+            prune_max_funds: args
+                .prune_max_funds
+                .unwrap_or_else(|| args.funds_threshold.unwrap()),
+            // An empty path turns the ledger off
+            ledger_path: args.ledger_path.filter(|path| !path.as_os_str().is_empty()),
+            claim_overflow_log_path: args
+                .claim_overflow_log_path
+                .filter(|path| !path.as_os_str().is_empty()),
         },
         proxies,
+        command: args.command,
     })
 }
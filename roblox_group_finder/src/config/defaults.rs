@@ -1,3 +1,5 @@
+use super::OwnerlessPolicy;
+
 pub const DEFAULT_FUNDS_THRESHOLD: u64 = 0;
 pub const DEFAULT_RETRY_LIMIT: usize = 5;
 pub const DEFAULT_SOCKS5_PATH: &str = "socks5.txt";
@@ -7,3 +9,10 @@ pub const DEFAULT_TIMEOUT: u64 = 30;
 pub const DEFAULT_CONNECT_TIMEOUT: u64 = 10;
 pub const DEFAULT_BATCH_WAIT: u64 = 625;
 pub const DEFAULT_DETAILED_WAIT: u64 = 8000;
+pub const DEFAULT_MIN_RECHECK: u64 = 0;
+pub const DEFAULT_MAX_RECHECK: u64 = 3600000;
+pub const DEFAULT_LATEST_MAX_GAP: usize = 1000;
+pub const DEFAULT_OWNERLESS_POLICY: OwnerlessPolicy = OwnerlessPolicy::Ignore;
+pub const DEFAULT_AUTO_PRUNE: bool = false;
+pub const DEFAULT_LEDGER_PATH: &str = "ledger.jsonl";
+pub const DEFAULT_CLAIM_OVERFLOW_LOG_PATH: &str = "claim_overflow_log.jsonl";
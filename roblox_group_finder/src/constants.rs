@@ -1,4 +1,34 @@
+use std::time::Duration;
+
 pub const MAX_IDS_IN_BATCH_REQUEST: usize = 100;
+pub const MAX_IDS_IN_USERS_BATCH_REQUEST: usize = 100;
 pub const RATE_LIMITED_MESSAGE: &str = "Too many requests";
 pub const CAPTCHA_MESSAGE: &str = "Challenge is required to authorize the request";
+pub const INVALID_GROUP_MESSAGE: &str = "Group is invalid or does not exist.";
+pub const ALREADY_OWNED_MESSAGE: &str = "Group already has an owner.";
 pub const BROWSER_ID_COOKIE_NAME: &str = "RBXEventTrackerV2";
+pub const OWNERSHIP_AGE_RECHECK_DIVISOR: u32 = 16;
+pub const IDLE_WAIT: Duration = Duration::from_millis(100);
+pub const CLAIM_RETRY_WAIT: Duration = Duration::from_secs(5);
+pub const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+// Discord allows 30 requests a minute per webhook
+pub const WEBHOOK_MIN_INTERVAL: u64 = 2000;
+pub const WEBHOOK_RETRY_LIMIT: usize = 3;
+pub const WEBHOOK_RETRY_WAIT: Duration = Duration::from_secs(1);
+pub const POST_CLAIM_HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+pub const CLAIM_STEP_RETRY_LIMIT: usize = 3;
+pub const CLAIM_STEP_RETRY_WAIT: Duration = Duration::from_secs(1);
+pub const CAPACITY_RECONCILE_INTERVAL: Duration = Duration::from_mins(1);
+// With pruning, free slots left when the capacity task starts pruning, so claims rarely wait
+pub const PRUNE_MARGIN: u16 = 1;
+pub const USERS_GROUPS_API_URL: &str = "https://groups.roblox.com/v2/users";
+pub const GROUPS_BATCH_API_URL: &str = "https://groups.roblox.com/v2/groups";
+pub const USERS_BATCH_API_URL: &str = "https://users.roblox.com/v1/users";
+pub const PLACES_BATCH_API_URL: &str = "https://games.roblox.com/v1/games/multiget-place-details";
+pub const MAX_IDS_IN_PLACES_BATCH_REQUEST: usize = 50;
+pub const ASSET_DETAILS_API_URL: &str = "https://economy.roblox.com/v2/assets";
+pub const BADGE_DETAILS_API_URL: &str = "https://badges.roblox.com/v1/badges";
+// Assets and badges are looked up one request each, this many at a time
+pub const MAX_CONCURRENT_ID_LOOKUPS: usize = 20;
+// Claims kept for the latency distribution
+pub const LATENCY_SAMPLE_LIMIT: usize = 256;
@@ -0,0 +1,159 @@
+use std::sync::atomic::{AtomicU16, AtomicU32, AtomicU64, Ordering};
+
+use kanal::{AsyncReceiver, Receiver, Sender};
+use roblox_api::apis::Id;
+use tokio::sync::{Mutex, Notify};
+use tokio_util::sync::CancellationToken;
+#[cfg(feature = "scripting")]
+use tracing::error;
+use tracing::warn;
+
+#[cfg(feature = "scripting")]
+use crate::policy::ScriptPolicy;
+use crate::{
+    catalog::OwnerlessCatalog,
+    client::{self, WebClient},
+    config::Settings,
+    events::FinderEvent,
+    hook::ClaimOutcome,
+    latency::LatencyStats,
+    ledger::{Ledger, LedgerEntry, Totals},
+    policy::{ClaimPolicy, FundsThreshold, RulePolicy},
+    scheduler::Scheduler,
+};
+
+#[derive(Debug, Default)]
+pub struct Counters {
+    pub groups_owned: AtomicU16,
+    pub group_limit: AtomicU16,
+    pub groups_claimed: AtomicU16,
+    pub batch_checks: AtomicU32,
+    pub batch_proxies: AtomicU32,
+    pub robux_claimed: AtomicU64,
+    pub locked_groups: AtomicU32,
+    pub deleted_groups: AtomicU32,
+    pub claims_attempted: AtomicU32,
+    pub claims_lost: AtomicU32,
+}
+
+impl Counters {
+    /// Share of claim requests that lost the race to another account.
+    pub fn lost_race_rate(&self) -> Option<f64> {
+        let attempted = self.claims_attempted.load(Ordering::Relaxed);
+        (attempted > 0)
+            .then(|| f64::from(self.claims_lost.load(Ordering::Relaxed)) / f64::from(attempted))
+    }
+}
+
+// Everything a finder's tasks share, so that several finders can run in one process
+#[derive(Debug)]
+pub struct FinderContext {
+    pub settings: Settings,
+    pub counters: Counters,
+    pub shutdown: CancellationToken,
+    pub scheduler: Scheduler,
+    pub catalog: OwnerlessCatalog,
+    pub policy: Box<dyn ClaimPolicy>,
+    pub ledger: Option<Ledger>,
+    pub latency: LatencyStats,
+    pub web: Box<dyn WebClient>,
+    // Wakes idle detailed check tasks when a group is sent to them
+    pub detection_ready: Notify,
+    // Held by a claim from its join until the owned group count is updated, and by capacity
+    // reconciliation from reading the count until it is corrected, so neither sees the other
+    // half done
+    pub capacity_turn: Mutex<()>,
+    // Wakes the capacity task after a claim, so it can prune before the next one needs a slot
+    pub capacity_changed: Notify,
+    // Wakes a claim waiting at the group limit once the capacity task is done pruning
+    pub slot_freed: Notify,
+    events: (Sender<FinderEvent>, Receiver<FinderEvent>),
+}
+
+impl FinderContext {
+    #[must_use]
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            counters: Counters::default(),
+            shutdown: CancellationToken::new(),
+            scheduler: Scheduler::new(settings.min_recheck, settings.max_recheck),
+            catalog: OwnerlessCatalog::default(),
+            policy: claim_policy(&settings),
+            ledger: None,
+            latency: LatencyStats::default(),
+            web: Box::new(client::http_client(&settings)),
+            detection_ready: Notify::new(),
+            capacity_turn: Mutex::new(()),
+            capacity_changed: Notify::new(),
+            slot_freed: Notify::new(),
+            events: kanal::unbounded(),
+            settings,
+        }
+    }
+
+    pub fn record(&self, group_id: Id, funds: Option<u64>, outcome: ClaimOutcome) {
+        let Some(ledger) = &self.ledger else {
+            return;
+        };
+        if let Err(error) = ledger.record(&LedgerEntry::now(group_id, funds, outcome)) {
+            warn!(
+                "Failed to record group {} in the ledger, error: {}",
+                group_id, error
+            );
+        }
+    }
+
+    /// Whether the ledger records the group as claimed and kept by the finder. Without a ledger,
+    /// no group is.
+    pub fn kept_by_finder(&self, id: Id) -> bool {
+        self.ledger
+            .as_ref()
+            .is_some_and(|ledger| ledger.is_kept(id))
+    }
+
+    /// Groups kept and robux claimed in this run, and in every run recorded in the ledger.
+    pub fn totals(&self) -> (Totals, Totals) {
+        let session = Totals {
+            groups: u64::from(self.counters.groups_claimed.load(Ordering::Relaxed)),
+            robux: self.counters.robux_claimed.load(Ordering::Relaxed),
+        };
+        let previous = self
+            .ledger
+            .as_ref()
+            .map(Ledger::previous)
+            .unwrap_or_default();
+        (session, previous + session)
+    }
+
+    pub fn emit(&self, event: FinderEvent) {
+        // The context owns a receiver, so the unbounded queue can't be closed
+        let _ = self.events.0.send(event);
+    }
+
+    #[must_use]
+    pub fn events(&self) -> AsyncReceiver<FinderEvent> {
+        self.events.1.clone_async()
+    }
+}
+
+fn claim_policy(settings: &Settings) -> Box<dyn ClaimPolicy> {
+    #[cfg(feature = "scripting")]
+    if let Some(script) = &settings.script {
+        match ScriptPolicy::load(script, default_policy(settings)) {
+            Ok(policy) => return Box::new(policy),
+            Err(error) => error!("{error:?}, using the default policy"),
+        }
+    }
+    default_policy(settings)
+}
+
+fn default_policy(settings: &Settings) -> Box<dyn ClaimPolicy> {
+    if settings.rules.is_empty() {
+        Box::new(FundsThreshold(settings.funds_threshold))
+    } else {
+        Box::new(RulePolicy {
+            rules: settings.rules.clone(),
+            fallback: FundsThreshold(settings.funds_threshold),
+        })
+    }
+}
@@ -0,0 +1,150 @@
+use async_trait::async_trait;
+use std::time::Duration;
+
+use kanal::{AsyncReceiver, AsyncSender};
+use roblox_api::apis::Id;
+use tokio::{task::JoinSet, time};
+use tracing::warn;
+
+use crate::threads::ClaimFailureReason;
+
+/// Something that happened to a group while the finder was running.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FinderEvent {
+    /// A public ownerless group was found and queued for claiming.
+    Detected { id: Id },
+    /// A group was claimed and kept.
+    Claimed { id: Id, funds: u64 },
+    /// A group was claimed, then left because the claim policy rejected it.
+    Left { id: Id, funds: u64 },
+    /// A group was claimed, but its funds couldn't be fetched, so it was kept.
+    FundsUnknown { id: Id },
+    /// Joining or claiming a group failed.
+    ClaimFailed { id: Id, reason: ClaimFailureReason },
+    /// Another account claimed a group first. `owner` is who owns it now, if that could be
+    /// checked.
+    ClaimLost { id: Id, owner: Option<Id> },
+    /// A group was joined but couldn't be claimed, so it was left to free its slot.
+    LeftUnclaimed { id: Id },
+    /// An owned group was left to make room for new claims, see [`crate::portfolio::lowest_value`].
+    Pruned { id: Id, funds: u64 },
+    /// Leaving a group failed, so it still takes up one of the account's slots.
+    LeaveFailed { id: Id },
+    /// The account owns as many groups as it can, so the finder is shutting down.
+    GroupLimitReached,
+    /// Every batch check task gave up after repeated errors, so no new groups will be found.
+    CheckTasksExhausted,
+    /// The finder hit an error it can't recover from and is shutting down.
+    Fatal { message: String },
+    /// The finder shut down, this is always the last event.
+    Stopped,
+}
+
+/// Receives every event a finder emits, in order.
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    async fn handle(&self, event: &FinderEvent);
+}
+
+/// Forwards events into a channel, backing [`crate::Finder::subscribe`].
+#[derive(Debug)]
+pub struct ChannelSink(AsyncSender<FinderEvent>);
+
+impl ChannelSink {
+    #[must_use]
+    pub const fn new(sender: AsyncSender<FinderEvent>) -> Self {
+        Self(sender)
+    }
+}
+
+#[async_trait]
+impl EventSink for ChannelSink {
+    async fn handle(&self, event: &FinderEvent) {
+        // Subscribers that dropped their receiver just stop getting events
+        let _ = self.0.send(event.clone()).await;
+    }
+}
+
+/// How long sinks get to handle their remaining events once the finder stops.
+const SINK_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Forwards events to every sink, each on its own task so a slow webhook or hook doesn't hold
+/// up the others.
+pub(crate) async fn dispatch(events: AsyncReceiver<FinderEvent>, sinks: Vec<Box<dyn EventSink>>) {
+    let mut tasks = JoinSet::new();
+    let senders: Vec<_> = sinks
+        .into_iter()
+        .map(|sink| {
+            let (sender, receiver) = kanal::unbounded_async();
+            tasks.spawn(run_sink(sink, receiver));
+            sender
+        })
+        .collect();
+    while let Ok(event) = events.recv().await {
+        let stopped = event == FinderEvent::Stopped;
+        for sender in &senders {
+            let _ = sender.send(event.clone()).await;
+        }
+        if stopped {
+            break;
+        }
+    }
+    if time::timeout(SINK_DRAIN_TIMEOUT, async {
+        while tasks.join_next().await.is_some() {}
+    })
+    .await
+    .is_err()
+    {
+        warn!("Event sinks didn't finish within {SINK_DRAIN_TIMEOUT:?}, dropping their events");
+    }
+}
+
+async fn run_sink(sink: Box<dyn EventSink>, events: AsyncReceiver<FinderEvent>) {
+    while let Ok(event) = events.recv().await {
+        sink.handle(&event).await;
+        if event == FinderEvent::Stopped {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use tokio::time;
+
+    use super::{dispatch, ChannelSink, EventSink, FinderEvent};
+    use crate::fake::id;
+
+    struct StalledSink;
+
+    #[async_trait]
+    impl EventSink for StalledSink {
+        async fn handle(&self, _event: &FinderEvent) {
+            time::sleep(Duration::from_hours(1)).await;
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn slow_sinks_dont_hold_up_the_others() {
+        let (sender, events) = kanal::unbounded_async();
+        let (subscriber, received) = kanal::unbounded_async();
+        let sinks: Vec<Box<dyn EventSink>> = vec![
+            Box::new(StalledSink),
+            Box::new(ChannelSink::new(subscriber)),
+        ];
+        sender
+            .send(FinderEvent::Detected { id: id(1) })
+            .await
+            .unwrap();
+        sender.send(FinderEvent::Stopped).await.unwrap();
+        dispatch(events, sinks).await;
+        assert_eq!(
+            received.recv().await.unwrap(),
+            FinderEvent::Detected { id: id(1) }
+        );
+        assert_eq!(received.recv().await.unwrap(), FinderEvent::Stopped);
+    }
+}
@@ -0,0 +1,339 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use async_trait::async_trait;
+use fxhash::{FxHashMap, FxHashSet};
+use roblox_api::apis::{groups::Metadata, Id};
+
+use crate::{
+    client::{
+        ApiFailure, ApiResult, BatchGroupInfo, CheckClient, ClaimClient, DetailedGroupInfo,
+        WebClient,
+    },
+    config::{OwnerlessPolicy, Settings},
+};
+
+pub fn id(id: u64) -> Id {
+    Id::new(id).unwrap()
+}
+
+pub fn settings() -> Settings {
+    Settings {
+        retry_limit: 3,
+        browser_id: String::new(),
+        funds_threshold: 10,
+        cookie: String::new(),
+        user_agent: String::new(),
+        timeout: Duration::from_secs(1),
+        connect_timeout: Duration::from_secs(1),
+        batch_wait: Duration::from_secs(1),
+        detailed_wait: Duration::from_millis(100),
+        min_recheck: Duration::from_mins(1),
+        max_recheck: Duration::from_hours(1),
+        ownerless_policy: OwnerlessPolicy::Recheck,
+        latest_hint: None,
+        latest_max_gap: 1000,
+        rules: Vec::new(),
+        script: None,
+        webhooks: Vec::new(),
+        post_claim_hook: None,
+        auto_prune: false,
+        prune_max_funds: 0,
+        ledger_path: None,
+        claim_overflow_log_path: None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FakeCall {
+    BatchInfo,
+    DetailedInfo,
+    Join,
+    Claim,
+    Funds,
+    Leave,
+    Metadata,
+    UserId,
+    Memberships,
+    Created,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FakeGroup {
+    pub owner: Option<Id>,
+    pub funds: u64,
+    pub public_entry_allowed: bool,
+    pub is_locked: bool,
+    pub members: FxHashSet<Id>,
+    pub name: String,
+    pub description: String,
+    pub created: Option<SystemTime>,
+}
+
+// A public, unlocked group without an owner
+pub fn group_info(group_id: u64, name: &str, member_count: u64) -> DetailedGroupInfo {
+    DetailedGroupInfo {
+        id: id(group_id),
+        owner: None,
+        public_entry_allowed: true,
+        is_locked: false,
+        name: name.to_owned(),
+        description: String::new(),
+        member_count,
+        created: None,
+    }
+}
+
+impl FakeGroup {
+    fn details(&self, id: Id) -> DetailedGroupInfo {
+        DetailedGroupInfo {
+            id,
+            owner: self.owner,
+            public_entry_allowed: self.public_entry_allowed,
+            is_locked: self.is_locked,
+            name: self.name.clone(),
+            description: self.description.clone(),
+            member_count: self.members.len() as u64,
+            // Like the real endpoints, only `WebClient::created` reports this
+            created: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct World {
+    groups: BTreeMap<Id, FakeGroup>,
+    group_limit: u16,
+    failures: FxHashMap<FakeCall, VecDeque<ApiFailure>>,
+    calls: FxHashMap<FakeCall, usize>,
+}
+impl World {
+    fn call(&mut self, call: FakeCall) -> ApiResult<()> {
+        *self.calls.entry(call).or_default() += 1;
+        self.failures
+            .get_mut(&call)
+            .and_then(VecDeque::pop_front)
+            .map_or(Ok(()), Err)
+    }
+
+    fn joined(&self, user_id: Id) -> usize {
+        self.groups
+            .values()
+            .filter(|group| group.members.contains(&user_id))
+            .count()
+    }
+
+    fn group_mut(&mut self, id: Id) -> ApiResult<&mut FakeGroup> {
+        self.groups.get_mut(&id).ok_or(ApiFailure::InvalidGroup)
+    }
+}
+
+// In-memory stand-in for the Roblox API, shared between clones like the real clients' connection pools
+#[derive(Debug, Clone)]
+pub struct FakeRoblox {
+    world: Arc<Mutex<World>>,
+    pub user_id: Id,
+}
+
+impl FakeRoblox {
+    pub fn new(user_id: Id, group_limit: u16) -> Self {
+        Self {
+            world: Arc::new(Mutex::new(World {
+                group_limit,
+                ..World::default()
+            })),
+            user_id,
+        }
+    }
+
+    pub fn insert_group(&self, id: Id, group: FakeGroup) {
+        self.world.lock().unwrap().groups.insert(id, group);
+    }
+
+    pub fn update_group(&self, id: Id, update: impl FnOnce(&mut FakeGroup)) {
+        update(self.world.lock().unwrap().groups.get_mut(&id).unwrap());
+    }
+
+    pub fn group(&self, id: Id) -> Option<FakeGroup> {
+        self.world.lock().unwrap().groups.get(&id).cloned()
+    }
+
+    pub fn fail_next(&self, call: FakeCall, failure: ApiFailure) {
+        self.world
+            .lock()
+            .unwrap()
+            .failures
+            .entry(call)
+            .or_default()
+            .push_back(failure);
+    }
+
+    pub fn calls(&self, call: FakeCall) -> usize {
+        self.world
+            .lock()
+            .unwrap()
+            .calls
+            .get(&call)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn request<T>(
+        &self,
+        call: FakeCall,
+        request: impl FnOnce(&mut World) -> ApiResult<T>,
+    ) -> ApiResult<T> {
+        let world = &mut *self.world.lock().unwrap();
+        world.call(call)?;
+        request(world)
+    }
+
+    pub fn groups_joined(&self) -> usize {
+        self.world.lock().unwrap().joined(self.user_id)
+    }
+
+    pub fn groups_owned(&self) -> usize {
+        self.world
+            .lock()
+            .unwrap()
+            .groups
+            .values()
+            .filter(|group| group.owner == Some(self.user_id))
+            .count()
+    }
+}
+
+#[async_trait]
+impl CheckClient for FakeRoblox {
+    async fn batch_info(&self, ids: &[Id]) -> ApiResult<Vec<BatchGroupInfo>> {
+        self.request(FakeCall::BatchInfo, |world| {
+            Ok(ids
+                .iter()
+                .filter_map(|id| {
+                    world.groups.get(id).map(|group| BatchGroupInfo {
+                        id: *id,
+                        owner: group.owner,
+                    })
+                })
+                .collect())
+        })
+    }
+
+    async fn detailed_info(&self, id: Id) -> ApiResult<DetailedGroupInfo> {
+        self.request(FakeCall::DetailedInfo, |world| {
+            Ok(world.group_mut(id)?.details(id))
+        })
+    }
+}
+
+#[async_trait]
+impl ClaimClient for FakeRoblox {
+    async fn join(&self, id: Id) -> ApiResult<()> {
+        self.request(FakeCall::Join, |world| {
+            let joined = world.joined(self.user_id);
+            let group_limit = world.group_limit as usize;
+            let group = world.group_mut(id)?;
+            if group.is_locked {
+                return Err(ApiFailure::Api("Group is locked.".to_owned()));
+            }
+            if !group.public_entry_allowed {
+                return Err(ApiFailure::Api(
+                    "You must be approved to join this group.".to_owned(),
+                ));
+            }
+            if group.members.contains(&self.user_id) {
+                return Err(ApiFailure::Api(
+                    "You are already a member of this group.".to_owned(),
+                ));
+            }
+            if joined >= group_limit {
+                return Err(ApiFailure::Api(
+                    "You are in the maximum number of groups.".to_owned(),
+                ));
+            }
+            group.members.insert(self.user_id);
+            Ok(())
+        })
+    }
+
+    async fn claim(&self, id: Id) -> ApiResult<()> {
+        self.request(FakeCall::Claim, |world| {
+            let group = world.group_mut(id)?;
+            if !group.members.contains(&self.user_id) {
+                return Err(ApiFailure::Api(
+                    "You are not a member of this group.".to_owned(),
+                ));
+            }
+            if group.owner.is_some() {
+                return Err(ApiFailure::AlreadyOwned);
+            }
+            group.owner = Some(self.user_id);
+            Ok(())
+        })
+    }
+
+    async fn owner(&self, id: Id) -> ApiResult<Option<Id>> {
+        Ok(self.detailed_info(id).await?.owner)
+    }
+
+    async fn funds(&self, id: Id) -> ApiResult<u64> {
+        self.request(FakeCall::Funds, |world| {
+            let group = world.group_mut(id)?;
+            if group.owner != Some(self.user_id) {
+                return Err(ApiFailure::Api("Insufficient permissions.".to_owned()));
+            }
+            Ok(group.funds)
+        })
+    }
+
+    async fn metadata(&self) -> ApiResult<Metadata> {
+        self.request(FakeCall::Metadata, |world| {
+            Ok(Metadata {
+                group_limit: world.group_limit,
+                current_group_count: u16::try_from(world.joined(self.user_id)).unwrap(),
+            })
+        })
+    }
+
+    async fn user_id(&self) -> ApiResult<Id> {
+        self.request(FakeCall::UserId, |_| Ok(self.user_id))
+    }
+
+    async fn leave(&self, id: Id, user_id: Id) -> ApiResult<()> {
+        self.request(FakeCall::Leave, |world| {
+            let group = world.group_mut(id)?;
+            if !group.members.remove(&user_id) {
+                return Err(ApiFailure::Api(
+                    "You are not a member of this group.".to_owned(),
+                ));
+            }
+            if group.owner == Some(user_id) {
+                group.owner = None;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[async_trait]
+impl WebClient for FakeRoblox {
+    async fn memberships(&self, user_id: Id) -> ApiResult<Vec<DetailedGroupInfo>> {
+        self.request(FakeCall::Memberships, |world| {
+            Ok(world
+                .groups
+                .iter()
+                .filter(|(_, group)| group.members.contains(&user_id))
+                .map(|(id, group)| group.details(*id))
+                .collect())
+        })
+    }
+
+    async fn created(&self, id: Id) -> ApiResult<Option<SystemTime>> {
+        self.request(FakeCall::Created, |world| {
+            Ok(world.groups.get(&id).and_then(|group| group.created))
+        })
+    }
+}
@@ -0,0 +1,349 @@
+use std::sync::{atomic::Ordering, Arc};
+
+use anyhow::{bail, ensure, Context, Result};
+use indicatif::ProgressBar;
+use kanal::AsyncReceiver;
+use tokio::task;
+use tracing::{info, warn};
+
+use crate::{
+    claim_queue::{self, ClaimQueue},
+    client::{CheckClient, ClaimClient, WebClient},
+    config::Settings,
+    context::FinderContext,
+    events::{self, ChannelSink, EventSink, FinderEvent},
+    hook::CommandSink,
+    init,
+    ledger::{self, Ledger},
+    policy::ClaimPolicy,
+    status_display, threads,
+    webhook::WebhookSink,
+};
+
+/// Configures a [`Finder`].
+pub struct FinderBuilder<C, A> {
+    settings: Settings,
+    check_clients: Vec<C>,
+    claim_client: Option<A>,
+    latest_group_id: Option<usize>,
+    sinks: Vec<Box<dyn EventSink>>,
+    status_bar: Option<ProgressBar>,
+    policy: Option<Box<dyn ClaimPolicy>>,
+    web_client: Option<Box<dyn WebClient>>,
+}
+
+impl<C: CheckClient + Clone + 'static, A: ClaimClient + 'static> FinderBuilder<C, A> {
+    /// Replaces the default [`crate::policy::FundsThreshold`] policy built from the settings.
+    #[must_use]
+    pub fn claim_policy(mut self, policy: impl ClaimPolicy + 'static) -> Self {
+        self.policy = Some(Box::new(policy));
+        self
+    }
+
+    /// Replaces the default HTTP client used for endpoints `roblox_api` has no wrapper for.
+    #[must_use]
+    pub fn web_client(mut self, client: impl WebClient + 'static) -> Self {
+        self.web_client = Some(Box::new(client));
+        self
+    }
+
+    /// Adds clients used for batch and detailed checks, usually one per proxy.
+    #[must_use]
+    pub fn check_clients(mut self, clients: impl IntoIterator<Item = C>) -> Self {
+        self.check_clients.extend(clients);
+        self
+    }
+
+    /// Sets the authenticated client that joins and claims groups.
+    #[must_use]
+    pub fn claim_client(mut self, client: A) -> Self {
+        self.claim_client = Some(client);
+        self
+    }
+
+    /// Sets the highest group id to check, see [`crate::utils::GroupsApiExt`] to find it.
+    #[must_use]
+    pub const fn latest_group_id(mut self, latest_group_id: usize) -> Self {
+        self.latest_group_id = Some(latest_group_id);
+        self
+    }
+
+    /// Adds a sink that receives every [`FinderEvent`].
+    #[must_use]
+    pub fn sink(mut self, sink: impl EventSink + 'static) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
+    /// Renders live counters into the given progress bar while running.
+    #[must_use]
+    pub fn status_bar(mut self, bar: ProgressBar) -> Self {
+        self.status_bar = Some(bar);
+        self
+    }
+
+    pub fn build(self) -> Result<Finder<C, A>> {
+        ensure!(!self.check_clients.is_empty(), "No check clients provided");
+        let Some(claim_client) = self.claim_client else {
+            bail!("No claim client provided");
+        };
+        let Some(latest_group_id) = self.latest_group_id else {
+            bail!("No latest group id provided");
+        };
+        let mut sinks = self.sinks;
+        sinks.extend(
+            self.settings
+                .webhooks
+                .iter()
+                .map(|webhook| Box::new(WebhookSink::new(webhook.clone())) as Box<dyn EventSink>),
+        );
+        if let Some(hook) = &self.settings.post_claim_hook {
+            sinks.push(Box::new(CommandSink::new(hook.clone())));
+        }
+        let ledger = self
+            .settings
+            .ledger_path
+            .as_deref()
+            .map(Ledger::open)
+            .transpose()?;
+        let mut ctx = FinderContext::new(self.settings);
+        ctx.ledger = ledger;
+        if let Some(policy) = self.policy {
+            ctx.policy = policy;
+        }
+        if let Some(web_client) = self.web_client {
+            ctx.web = web_client;
+        }
+        Ok(Finder {
+            ctx: Arc::new(ctx),
+            check_clients: self.check_clients,
+            claim_client,
+            latest_group_id,
+            sinks,
+            status_bar: self.status_bar,
+        })
+    }
+}
+
+/// Checks groups for missing owners and claims them until the account is full or it is shut down.
+pub struct Finder<C, A> {
+    ctx: Arc<FinderContext>,
+    check_clients: Vec<C>,
+    claim_client: A,
+    latest_group_id: usize,
+    sinks: Vec<Box<dyn EventSink>>,
+    status_bar: Option<ProgressBar>,
+}
+
+impl<C: CheckClient + Clone + 'static, A: ClaimClient + 'static> Finder<C, A> {
+    #[must_use]
+    pub fn builder(settings: Settings) -> FinderBuilder<C, A> {
+        FinderBuilder {
+            settings,
+            check_clients: Vec::new(),
+            claim_client: None,
+            latest_group_id: None,
+            sinks: Vec::new(),
+            status_bar: None,
+            policy: None,
+            web_client: None,
+        }
+    }
+
+    /// The state shared by the finder's tasks, including its counters and shutdown token.
+    pub const fn context(&self) -> &Arc<FinderContext> {
+        &self.ctx
+    }
+
+    /// Returns a stream of every event emitted after [`Finder::run`] starts.
+    pub fn subscribe(&mut self) -> AsyncReceiver<FinderEvent> {
+        let (sender, receiver) = kanal::unbounded_async();
+        self.sinks.push(Box::new(ChannelSink::new(sender)));
+        receiver
+    }
+
+    /// Runs until the account reaches its group limit or the context is shut down.
+    pub async fn run(self) -> Result<()> {
+        let dispatcher = task::spawn(events::dispatch(self.ctx.events(), self.sinks));
+        let result = Self::find(
+            &self.ctx,
+            self.check_clients,
+            self.claim_client,
+            self.latest_group_id,
+            self.status_bar,
+        )
+        .await;
+        if let Err(error) = &result {
+            self.ctx.emit(FinderEvent::Fatal {
+                message: format!("{error:#}"),
+            });
+        }
+        self.ctx.shutdown.cancel();
+        self.ctx.emit(FinderEvent::Stopped);
+        dispatcher.await?;
+        let (session, lifetime) = self.ctx.totals();
+        info!(
+            "Claimed {} groups with {} robux this run, {} groups with {} robux in total",
+            session.groups, session.robux, lifetime.groups, lifetime.robux
+        );
+        if let Some(summary) = self.ctx.latency.summary() {
+            info!(
+                "Claim latency {}, {} claims lost ({:.1}% of attempts)",
+                summary,
+                self.ctx.counters.claims_lost.load(Ordering::Relaxed),
+                self.ctx.counters.lost_race_rate().unwrap_or_default() * 100f64
+            );
+        }
+        result
+    }
+
+    async fn find(
+        ctx: &Arc<FinderContext>,
+        check_clients: Vec<C>,
+        claim_client: A,
+        latest_group_id: usize,
+        status_bar: Option<ProgressBar>,
+    ) -> Result<()> {
+        let metadata = claim_client
+            .metadata()
+            .await
+            .with_context(|| "Failed to get group metadata")?;
+        if metadata.group_limit == 0 {
+            bail!("Auth cookie provided is invalid");
+        }
+        let user_id = claim_client
+            .user_id()
+            .await
+            .with_context(|| "Failed to get account's user ID")?;
+
+        if let Some(ledger) = &ctx.ledger {
+            match ledger.reconcile(&claim_client, &*ctx.web, user_id).await {
+                Ok(reconciliation) => ledger::log_reconciliation(&reconciliation),
+                Err(error) => warn!("Failed to reconcile the ledger, error: {:?}", error),
+            }
+        }
+
+        let capacity = metadata.group_limit.into();
+        let (claim_queue, recheck) = match &ctx.settings.claim_overflow_log_path {
+            Some(path) => {
+                let recheck = claim_queue::read_overflow_log(path)?;
+                (ClaimQueue::with_overflow_log(capacity, path)?, recheck)
+            }
+            None => (ClaimQueue::new(capacity), Vec::new()),
+        };
+        let claim_queue = Arc::new(claim_queue);
+        init::start_check_tasks(ctx, latest_group_id, check_clients, &claim_queue, &recheck);
+        if let Some(bar) = status_bar {
+            info!("Starting status display");
+            task::spawn(status_display::status_thread(bar, ctx.clone()));
+        }
+
+        info!("Starting claim task");
+        threads::claim(
+            claim_client,
+            ctx.clone(),
+            claim_queue.clone(),
+            metadata,
+            user_id,
+        )
+        .await;
+        if let Err(error) = claim_queue.save_pending() {
+            warn!("Failed to save the claim queue, error: {:?}", error);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, time::Duration};
+
+    use tokio::time;
+
+    use super::Finder;
+    use crate::{
+        config::{OwnerlessPolicy, Settings},
+        events::FinderEvent,
+        fake::{id, settings, FakeGroup, FakeRoblox},
+    };
+
+    #[tokio::test(start_paused = true)]
+    async fn runs_until_the_group_limit_and_reports_events() {
+        let fake = FakeRoblox::new(id(1), 1);
+        for group_id in 1..=3 {
+            fake.insert_group(
+                id(group_id),
+                FakeGroup {
+                    owner: (group_id != 2).then(|| id(2)),
+                    funds: 100,
+                    public_entry_allowed: true,
+                    ..FakeGroup::default()
+                },
+            );
+        }
+        let mut finder = Finder::builder(settings())
+            .check_clients([fake.clone()])
+            .claim_client(fake.clone())
+            .web_client(fake.clone())
+            .latest_group_id(3)
+            .build()
+            .unwrap();
+        let events = finder.subscribe();
+        finder.run().await.unwrap();
+
+        assert_eq!(fake.group(id(2)).unwrap().owner, Some(fake.user_id));
+        assert_eq!(
+            events.try_recv().unwrap(),
+            Some(FinderEvent::Detected { id: id(2) })
+        );
+        assert_eq!(
+            events.try_recv().unwrap(),
+            Some(FinderEvent::Claimed {
+                id: id(2),
+                funds: 100
+            })
+        );
+        assert_eq!(
+            events.try_recv().unwrap(),
+            Some(FinderEvent::GroupLimitReached)
+        );
+        assert_eq!(events.try_recv().unwrap(), Some(FinderEvent::Stopped));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn claims_groups_left_in_the_overflow_log_by_the_last_run() {
+        let path = env::temp_dir().join(format!("finder_overflow_{}.jsonl", std::process::id()));
+        fs::write(&path, "{\"group_id\":2}\n").unwrap();
+        let fake = FakeRoblox::new(id(1), 1);
+        fake.insert_group(id(1), FakeGroup::default());
+        // Already ownerless when the finder starts, so only the log gets it claimed
+        fake.insert_group(
+            id(2),
+            FakeGroup {
+                funds: 100,
+                public_entry_allowed: true,
+                ..FakeGroup::default()
+            },
+        );
+        let finder = Finder::builder(Settings {
+            claim_overflow_log_path: Some(path.clone()),
+            ownerless_policy: OwnerlessPolicy::Ignore,
+            ..settings()
+        })
+        .check_clients([fake.clone()])
+        .claim_client(fake.clone())
+        .web_client(fake.clone())
+        .latest_group_id(2)
+        .build()
+        .unwrap();
+        time::timeout(Duration::from_secs(30), finder.run())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(fake.group(id(2)).unwrap().owner, Some(fake.user_id));
+        // Nothing is left waiting, so the log is emptied
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,229 @@
+use std::{process::Stdio, time::Duration};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncWriteExt, process::Command, time};
+use tracing::{info, warn};
+
+use crate::{
+    constants::POST_CLAIM_HOOK_TIMEOUT,
+    events::{EventSink, FinderEvent},
+};
+
+// The `[post_claim_hook]` section
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct HookConfig {
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// How long the command may run before it is killed, in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaimOutcome {
+    Kept,
+    Left,
+    /// Left later to free a slot, only recorded in the ledger.
+    Pruned,
+    /// Found missing or owned by someone else when reconciling the ledger, only recorded in it.
+    Lost,
+    /// Owned by the account but missing from the ledger when reconciling it, only recorded in
+    /// it. Not counted as claimed, since the finder may not have claimed it.
+    Recovered,
+}
+
+impl ClaimOutcome {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Kept => "kept",
+            Self::Left => "left",
+            Self::Pruned => "pruned",
+            Self::Lost => "lost",
+            Self::Recovered => "recovered",
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct HookInput {
+    group_id: u64,
+    /// Missing when the group was kept because its funds couldn't be fetched.
+    funds: Option<u64>,
+    outcome: ClaimOutcome,
+}
+
+/// Runs a command after every claim, passing the group id, its funds and whether it was kept.
+///
+/// The details are set as the `GROUP_ID`, `GROUP_FUNDS` and `CLAIM_OUTCOME` environment
+/// variables, and written to the command's stdin as a JSON object. `GROUP_FUNDS` is unset, and
+/// `funds` is null, when a group was kept because its funds couldn't be fetched.
+#[derive(Debug)]
+pub struct CommandSink {
+    command: String,
+    args: Vec<String>,
+    timeout: Duration,
+}
+
+impl CommandSink {
+    #[must_use]
+    pub fn new(config: HookConfig) -> Self {
+        Self {
+            command: config.command,
+            args: config.args,
+            timeout: config
+                .timeout
+                .map_or(POST_CLAIM_HOOK_TIMEOUT, Duration::from_millis),
+        }
+    }
+
+    async fn run(&self, input: &HookInput) -> anyhow::Result<std::process::Output> {
+        let mut command = Command::new(&self.command);
+        if let Some(funds) = input.funds {
+            command.env("GROUP_FUNDS", funds.to_string());
+        }
+        let mut child = command
+            .args(&self.args)
+            .env("GROUP_ID", input.group_id.to_string())
+            .env("CLAIM_OUTCOME", input.outcome.as_str())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // Commands that only read the environment may exit without reading stdin
+            let _ = stdin.write_all(&serde_json::to_vec(input)?).await;
+        }
+        Ok(child.wait_with_output().await?)
+    }
+}
+
+#[async_trait]
+impl EventSink for CommandSink {
+    async fn handle(&self, event: &FinderEvent) {
+        let input = match *event {
+            FinderEvent::Claimed { id, funds } => HookInput {
+                group_id: id.get(),
+                funds: Some(funds),
+                outcome: ClaimOutcome::Kept,
+            },
+            FinderEvent::FundsUnknown { id } => HookInput {
+                group_id: id.get(),
+                funds: None,
+                outcome: ClaimOutcome::Kept,
+            },
+            FinderEvent::Left { id, funds } => HookInput {
+                group_id: id.get(),
+                funds: Some(funds),
+                outcome: ClaimOutcome::Left,
+            },
+            _ => return,
+        };
+        match time::timeout(self.timeout, self.run(&input)).await {
+            Ok(Ok(output)) if output.status.success() => info!(
+                "Post-claim hook for group {} exited with {}",
+                input.group_id, output.status
+            ),
+            Ok(Ok(output)) => warn!(
+                "Post-claim hook for group {} exited with {}, stderr: {}",
+                input.group_id,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim_end()
+            ),
+            Ok(Err(error)) => warn!(
+                "Failed to run post-claim hook for group {}, error: {:?}",
+                input.group_id, error
+            ),
+            Err(_) => warn!(
+                "Post-claim hook for group {} timed out after {:?} and was killed",
+                input.group_id, self.timeout
+            ),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{env, fs, time::Duration};
+
+    use tokio::time::Instant;
+
+    use super::{CommandSink, HookConfig};
+    use crate::{
+        events::{EventSink, FinderEvent},
+        fake::id,
+    };
+
+    fn sink(script: &str, timeout: u64) -> CommandSink {
+        CommandSink::new(HookConfig {
+            command: "sh".to_owned(),
+            args: vec!["-c".to_owned(), script.to_owned()],
+            timeout: Some(timeout),
+        })
+    }
+
+    #[tokio::test]
+    async fn passes_claim_details_to_the_command() {
+        let output = env::temp_dir().join(format!("post_claim_hook_{}", std::process::id()));
+        let sink = sink(
+            &format!(
+                "echo \"$GROUP_ID $GROUP_FUNDS $CLAIM_OUTCOME\" > {0}; cat >> {0}",
+                output.display()
+            ),
+            5000,
+        );
+
+        sink.handle(&FinderEvent::Detected { id: id(3) }).await;
+        assert!(!output.exists());
+        sink.handle(&FinderEvent::Left {
+            id: id(3),
+            funds: 7,
+        })
+        .await;
+
+        let written = fs::read_to_string(&output).unwrap();
+        fs::remove_file(&output).unwrap();
+        assert_eq!(
+            written,
+            "3 7 left\n{\"group_id\":3,\"funds\":7,\"outcome\":\"left\"}"
+        );
+    }
+
+    #[tokio::test]
+    async fn leaves_funds_unset_when_unknown() {
+        let output =
+            env::temp_dir().join(format!("post_claim_hook_unknown_{}", std::process::id()));
+        let sink = sink(
+            &format!(
+                "echo \"$GROUP_ID ${{GROUP_FUNDS-unset}} $CLAIM_OUTCOME\" > {0}; cat >> {0}",
+                output.display()
+            ),
+            5000,
+        );
+
+        sink.handle(&FinderEvent::FundsUnknown { id: id(4) }).await;
+
+        let written = fs::read_to_string(&output).unwrap();
+        fs::remove_file(&output).unwrap();
+        assert_eq!(
+            written,
+            "4 unset kept\n{\"group_id\":4,\"funds\":null,\"outcome\":\"kept\"}"
+        );
+    }
+
+    #[tokio::test]
+    async fn kills_commands_that_time_out() {
+        let start = Instant::now();
+        sink("sleep 10", 100)
+            .handle(&FinderEvent::Claimed {
+                id: id(3),
+                funds: 7,
+            })
+            .await;
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
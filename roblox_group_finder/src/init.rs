@@ -1,92 +1,104 @@
-use std::sync::atomic::Ordering;
+use std::sync::{atomic::Ordering, Arc};
 
-use indicatif::ProgressBar;
+use fxhash::FxHashSet;
 use kanal::{Receiver, Sender};
 use roblox_api::{
     apis::Id,
     clients::{Client, ClientBuilder, Proxy},
 };
-use tokio::task;
+use tokio::{task, time::Instant};
 use tracing::{info, warn};
 
 use crate::{
-    status_display::{self, BATCH_PROXIES},
-    threads::{self, TrackedGroup},
-    SETTINGS,
+    claim_queue::ClaimQueue,
+    client::CheckClient,
+    config::Settings,
+    context::FinderContext,
+    scheduler::ScheduledGroup,
+    threads::{self, Detection, GroupState, TrackedGroup},
 };
 
 #[allow(clippy::cast_possible_truncation)]
-pub fn init_check_threads(
+pub fn start_check_tasks<C: CheckClient + Clone + 'static>(
+    ctx: &Arc<FinderContext>,
     latest_group_id: usize,
-    bar: &ProgressBar,
-    proxies: &str,
-    group_limit: u16,
-) -> Receiver<Id> {
-    let settings = SETTINGS.get().unwrap();
-    let batch_check_queue: (Sender<TrackedGroup>, Receiver<TrackedGroup>) =
-        kanal::bounded(latest_group_id);
-    let batch_priority_check_queue: (Sender<TrackedGroup>, Receiver<TrackedGroup>) =
-        kanal::bounded(latest_group_id);
-    let detailed_check_queue: (Sender<Id>, Receiver<Id>) = kanal::bounded(latest_group_id);
-    let detailed_priority_check_queue: (Sender<Id>, Receiver<Id>) = kanal::bounded(latest_group_id);
-    let claim_queue: (Sender<Id>, Receiver<Id>) = kanal::bounded(group_limit as usize);
-
-    {
-        let batch_senders = (
-            batch_check_queue.0.clone(),
-            batch_priority_check_queue.0.clone(),
-        );
-        info!("Starting status display");
-
-        task::spawn(status_display::status_thread(
-            bar.clone(),
-            group_limit,
-            batch_senders,
-        ));
-    }
+    clients: Vec<C>,
+    claim_queue: &Arc<ClaimQueue>,
+    recheck: &[Id],
+) {
+    // Unbounded so the queues only grow with the groups actually found
+    let detailed_check_queue: (Sender<Detection>, Receiver<Detection>) = kanal::unbounded();
+    let detailed_priority_check_queue: (Sender<Detection>, Receiver<Detection>) =
+        kanal::unbounded();
 
     info!("Initializing check queue");
-    for id in 1..=(latest_group_id as u64) {
-        batch_check_queue
-            .0
-            .send(TrackedGroup {
-                id: Id::new(id).unwrap(),
-                processed_before: false,
-            })
-            .unwrap();
+    let now = Instant::now();
+    let rechecked: FxHashSet<Id> = recheck.iter().copied().collect();
+    ctx.scheduler.schedule_all(
+        (1..=(latest_group_id as u64))
+            .map(|id| Id::new(id).unwrap())
+            .filter(|id| !rechecked.contains(id))
+            .map(|id| ScheduledGroup {
+                next_check: now,
+                group: TrackedGroup {
+                    id,
+                    ..Default::default()
+                },
+            }),
+    );
+    // Candidates left over from the last run are already ownerless, so as unseen groups they
+    // would be left to the ownerless policy instead of being claimed
+    if !recheck.is_empty() {
+        info!("Checking {} groups left in the claim queue", recheck.len());
+    }
+    for &id in recheck {
+        let _ = detailed_priority_check_queue.0.send(Detection {
+            group: TrackedGroup {
+                id,
+                state: GroupState::Ownerless { since: now },
+            },
+            observed: now,
+        });
     }
 
     info!("Starting check tasks");
-    let proxies = proxies.lines().collect::<Vec<&str>>();
-    BATCH_PROXIES.store(proxies.len() as u32, Ordering::Relaxed);
-    for proxy in proxies {
+    ctx.counters
+        .batch_proxies
+        .store(clients.len() as u32, Ordering::Relaxed);
+    for client in clients {
+        task::spawn(threads::batch_check(
+            client.clone(),
+            ctx.clone(),
+            detailed_check_queue.0.clone(),
+        ));
+        task::spawn(threads::detailed_check(
+            client,
+            ctx.clone(),
+            detailed_check_queue.1.clone(),
+            (
+                detailed_priority_check_queue.0.clone(),
+                detailed_priority_check_queue.1.clone(),
+            ),
+            claim_queue.clone(),
+        ));
+    }
+    info!("Finished starting check tasks");
+}
+
+pub fn proxy_clients(proxies: &str, settings: &Settings) -> Vec<Client> {
+    let mut clients = Vec::new();
+    for proxy in proxies.lines() {
         if let Ok(proxy) = Proxy::all(proxy) {
-            let client = Client::new(
+            clients.push(Client::new(
                 ClientBuilder::new()
                     .proxy(proxy)
                     .connect_timeout(settings.connect_timeout)
                     .timeout(settings.timeout)
                     .http2_prior_knowledge(),
-            );
-            task::spawn(threads::batch_check(
-                client.clone(),
-                batch_check_queue.clone(),
-                batch_priority_check_queue.clone(),
-                detailed_check_queue.0.clone(),
-            ));
-            task::spawn(threads::detailed_check(
-                client,
-                detailed_check_queue.1.clone(),
-                (
-                    detailed_priority_check_queue.0.clone(),
-                    detailed_priority_check_queue.1.clone(),
-                ),
-                claim_queue.0.clone(),
             ));
         } else {
             warn!("Failed to create task with proxy URL {}", proxy);
         }
     }
-    info!("Finished starting check tasks");
-    claim_queue.1
+    clients
 }
@@ -0,0 +1,172 @@
+use std::{collections::VecDeque, fmt, sync::Mutex, time::Duration};
+
+use tokio::time::Instant;
+
+use crate::constants::LATENCY_SAMPLE_LIMIT;
+
+/// When a detection passed through the check stages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DetectionTimes {
+    /// The batch check saw the group without an owner
+    pub observed: Instant,
+    /// The detailed check confirmed it can be claimed
+    pub detailed: Instant,
+}
+
+/// How long a detection spent in each stage before the claim request was sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageLatencies {
+    pub detailed: Duration,
+    pub queued: Duration,
+    pub claim: Duration,
+}
+
+impl StageLatencies {
+    #[must_use]
+    pub fn new(times: &DetectionTimes, dequeued: Instant, claim_sent: Instant) -> Self {
+        Self {
+            detailed: times.detailed.saturating_duration_since(times.observed),
+            queued: dequeued.saturating_duration_since(times.detailed),
+            claim: claim_sent.saturating_duration_since(dequeued),
+        }
+    }
+
+    #[must_use]
+    pub fn total(&self) -> Duration {
+        self.detailed + self.queued + self.claim
+    }
+}
+
+impl fmt::Display for StageLatencies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}ms (detailed {}ms, queued {}ms, claim {}ms)",
+            self.total().as_millis(),
+            self.detailed.as_millis(),
+            self.queued.as_millis(),
+            self.claim.as_millis()
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Percentiles {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub max: Duration,
+}
+
+impl Percentiles {
+    fn of(mut samples: Vec<Duration>) -> Self {
+        samples.sort_unstable();
+        let at = |percent: usize| samples[(samples.len() - 1) * percent / 100];
+        Self {
+            p50: at(50),
+            p90: at(90),
+            max: at(100),
+        }
+    }
+}
+
+impl fmt::Display for Percentiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}/{}ms",
+            self.p50.as_millis(),
+            self.p90.as_millis(),
+            self.max.as_millis()
+        )
+    }
+}
+
+/// Latency distribution of recent claims, per stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencySummary {
+    pub samples: usize,
+    pub detailed: Percentiles,
+    pub queued: Percentiles,
+    pub claim: Percentiles,
+    pub total: Percentiles,
+}
+
+impl fmt::Display for LatencySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "p50/p90/max {} (detailed {}, queued {}, claim {}) over {} claims",
+            self.total, self.detailed, self.queued, self.claim, self.samples
+        )
+    }
+}
+
+// Keeps the latest claims only, so the distribution follows changes in pacing
+#[derive(Debug, Default)]
+pub struct LatencyStats {
+    recent: Mutex<VecDeque<StageLatencies>>,
+}
+
+impl LatencyStats {
+    pub fn record(&self, latencies: StageLatencies) {
+        let recent = &mut *self.recent.lock().unwrap();
+        if recent.len() >= LATENCY_SAMPLE_LIMIT {
+            recent.pop_front();
+        }
+        recent.push_back(latencies);
+    }
+
+    pub fn summary(&self) -> Option<LatencySummary> {
+        let recent = self.recent.lock().unwrap().clone();
+        if recent.is_empty() {
+            return None;
+        }
+        let stage = |get: fn(&StageLatencies) -> Duration| {
+            Percentiles::of(recent.iter().map(get).collect())
+        };
+        Some(LatencySummary {
+            samples: recent.len(),
+            detailed: stage(|latencies| latencies.detailed),
+            queued: stage(|latencies| latencies.queued),
+            claim: stage(|latencies| latencies.claim),
+            total: stage(StageLatencies::total),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+
+    use super::{DetectionTimes, LatencyStats, StageLatencies};
+    use crate::constants::LATENCY_SAMPLE_LIMIT;
+
+    #[test]
+    fn summary_covers_the_latest_samples_per_stage() {
+        let stats = LatencyStats::default();
+        assert_eq!(stats.summary(), None);
+        let observed = Instant::now();
+        let ms = Duration::from_millis;
+        for step in 0..LATENCY_SAMPLE_LIMIT as u64 + 10 {
+            let times = DetectionTimes {
+                observed,
+                detailed: observed + ms(step),
+            };
+            stats.record(StageLatencies::new(
+                &times,
+                times.detailed + ms(1),
+                times.detailed + ms(3),
+            ));
+        }
+        let summary = stats.summary().unwrap();
+        assert_eq!(summary.samples, LATENCY_SAMPLE_LIMIT);
+        // The first ten samples were dropped
+        assert_eq!(summary.detailed.max, ms(LATENCY_SAMPLE_LIMIT as u64 + 9));
+        assert_eq!(summary.detailed.p50.as_millis(), 10 + 255 / 2);
+        assert_eq!(summary.queued.p90, ms(1));
+        assert_eq!(summary.claim.max, ms(2));
+        assert_eq!(summary.total.max, ms(LATENCY_SAMPLE_LIMIT as u64 + 12));
+    }
+}
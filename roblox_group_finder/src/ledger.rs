@@ -0,0 +1,379 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use fxhash::FxHashSet;
+use roblox_api::apis::Id;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    client::{ClaimClient, WebClient},
+    hook::ClaimOutcome,
+    portfolio::{self, Holding},
+};
+
+/// One line of the ledger, written when a claimed group is kept, left or pruned, and when
+/// reconciling finds a group lost or recovered.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LedgerEntry {
+    pub group_id: Id,
+    /// `None` if the funds couldn't be fetched after claiming.
+    pub funds: Option<u64>,
+    pub outcome: ClaimOutcome,
+    /// Seconds since the Unix epoch.
+    pub recorded_at: u64,
+}
+
+impl LedgerEntry {
+    #[must_use]
+    pub fn now(group_id: Id, funds: Option<u64>, outcome: ClaimOutcome) -> Self {
+        Self {
+            group_id,
+            funds,
+            outcome,
+            recorded_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Totals {
+    pub groups: u64,
+    pub robux: u64,
+}
+
+impl Totals {
+    #[must_use]
+    pub fn of<'a>(entries: impl IntoIterator<Item = &'a LedgerEntry>) -> Self {
+        entries
+            .into_iter()
+            .filter(|entry| entry.outcome == ClaimOutcome::Kept)
+            .fold(Self::default(), |totals, entry| Self {
+                groups: totals.groups + 1,
+                robux: totals.robux + entry.funds.unwrap_or_default(),
+            })
+    }
+}
+
+impl std::ops::Add for Totals {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            groups: self.groups + other.groups,
+            robux: self.robux + other.robux,
+        }
+    }
+}
+
+// Outcomes after which the account should still own the group
+const fn held(outcome: ClaimOutcome) -> bool {
+    matches!(outcome, ClaimOutcome::Kept | ClaimOutcome::Recovered)
+}
+
+/// What comparing the ledger with the account's groups found.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Reconciliation {
+    /// Kept or recovered groups the account still owns.
+    pub confirmed: usize,
+    /// Kept or recovered groups the account is no longer a member of.
+    pub lost: Vec<Id>,
+    /// Kept or recovered groups the account is still in, with their current owner.
+    pub transferred: Vec<(Id, Option<Id>)>,
+    /// Groups the account owns that the ledger has no record of.
+    pub unrecorded: Vec<Holding>,
+}
+
+impl Reconciliation {
+    #[must_use]
+    pub fn new(entries: &[LedgerEntry], holdings: &[Holding]) -> Self {
+        let mut last_outcomes = HashMap::new();
+        for entry in entries {
+            last_outcomes.insert(entry.group_id, entry.outcome);
+        }
+        let memberships: HashMap<Id, &Holding> = holdings
+            .iter()
+            .map(|holding| (holding.group.id, holding))
+            .collect();
+
+        let mut reconciliation = Self::default();
+        let mut held_ids: Vec<Id> = last_outcomes
+            .iter()
+            .filter(|(_, outcome)| held(**outcome))
+            .map(|(id, _)| *id)
+            .collect();
+        held_ids.sort_unstable();
+        for id in held_ids {
+            match memberships.get(&id) {
+                Some(holding) if holding.owned => reconciliation.confirmed += 1,
+                Some(holding) => reconciliation.transferred.push((id, holding.group.owner)),
+                None => reconciliation.lost.push(id),
+            }
+        }
+        reconciliation.unrecorded = holdings
+            .iter()
+            .filter(|holding| {
+                holding.owned
+                    && !last_outcomes
+                        .get(&holding.group.id)
+                        .is_some_and(|outcome| held(*outcome))
+            })
+            .cloned()
+            .collect();
+        reconciliation
+    }
+}
+
+/// An append-only file of every claim, one JSON object per line, kept across runs.
+#[derive(Debug)]
+pub struct Ledger {
+    path: PathBuf,
+    file: Mutex<File>,
+    previous: Mutex<Totals>,
+    // Groups whose last entry is `Kept`
+    kept: Mutex<FxHashSet<Id>>,
+}
+
+impl Ledger {
+    pub fn open(path: &Path) -> Result<Self> {
+        let entries = read_entries(path)?;
+        let previous = Totals::of(&entries);
+        let mut kept = FxHashSet::default();
+        for entry in &entries {
+            track_kept(&mut kept, entry);
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open ledger at {}", path.display()))?;
+        Ok(Self {
+            path: path.to_owned(),
+            file: Mutex::new(file),
+            previous: Mutex::new(previous),
+            kept: Mutex::new(kept),
+        })
+    }
+
+    /// Whether the finder claimed and kept the group, and hasn't left or lost it since.
+    pub fn is_kept(&self, id: Id) -> bool {
+        self.kept.lock().unwrap().contains(&id)
+    }
+
+    /// Totals of the runs before this one.
+    pub fn previous(&self) -> Totals {
+        *self.previous.lock().unwrap()
+    }
+
+    pub fn record(&self, entry: &LedgerEntry) -> io::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        self.file.lock().unwrap().write_all(&line)?;
+        track_kept(&mut self.kept.lock().unwrap(), entry);
+        Ok(())
+    }
+
+    /// Compares the ledger with the groups the account is in, marking kept groups that are
+    /// gone as lost and recording owned groups that the ledger missed as recovered.
+    pub async fn reconcile(
+        &self,
+        client: &impl ClaimClient,
+        web: &(impl WebClient + ?Sized),
+        user_id: Id,
+    ) -> Result<Reconciliation> {
+        let entries = read_entries(&self.path)?;
+        let holdings = portfolio::holdings(client, web, user_id)
+            .await
+            .with_context(|| "Failed to get the account's groups")?;
+        let reconciliation = Reconciliation::new(&entries, &holdings);
+
+        let lost = reconciliation
+            .lost
+            .iter()
+            .chain(reconciliation.transferred.iter().map(|(id, _)| id));
+        for id in lost {
+            self.record(&LedgerEntry::now(*id, None, ClaimOutcome::Lost))?;
+        }
+        // Recovered groups stay out of the totals, which only count the finder's own claims
+        for holding in &reconciliation.unrecorded {
+            self.record(&LedgerEntry::now(
+                holding.group.id,
+                holding.funds,
+                ClaimOutcome::Recovered,
+            ))?;
+        }
+        Ok(reconciliation)
+    }
+}
+
+pub fn log_reconciliation(reconciliation: &Reconciliation) {
+    info!(
+        "Ledger reconciliation: {} groups confirmed, {} lost, {} transferred, {} unrecorded",
+        reconciliation.confirmed,
+        reconciliation.lost.len(),
+        reconciliation.transferred.len(),
+        reconciliation.unrecorded.len()
+    );
+    for id in &reconciliation.lost {
+        warn!("Group {} in the ledger is no longer in the account", id);
+    }
+    for (id, owner) in &reconciliation.transferred {
+        if let Some(owner) = owner {
+            warn!("Group {} in the ledger is now owned by {}", id, owner);
+        } else {
+            warn!("Group {} in the ledger no longer has an owner", id);
+        }
+    }
+    for holding in &reconciliation.unrecorded {
+        info!(
+            "Recorded group {} that was missing from the ledger ({} robux)",
+            holding.group.id,
+            holding.funds.unwrap_or_default()
+        );
+    }
+}
+
+fn track_kept(kept: &mut FxHashSet<Id>, entry: &LedgerEntry) {
+    if entry.outcome == ClaimOutcome::Kept {
+        kept.insert(entry.group_id);
+    } else {
+        kept.remove(&entry.group_id);
+    }
+}
+
+/// Reads every entry of the ledger at `path`, which is empty if the file doesn't exist yet.
+///
+/// A line cut short by a crash is skipped rather than failing the whole read.
+pub fn read_entries(path: &Path) -> Result<Vec<LedgerEntry>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => {
+            return Err(error)
+                .with_context(|| format!("Failed to read ledger at {}", path.display()))
+        }
+    };
+    Ok(contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(index, line)| {
+            serde_json::from_str(line)
+                .inspect_err(|error| {
+                    warn!("Skipping ledger line {}, error: {}", index + 1, error);
+                })
+                .ok()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, io::Write};
+
+    use super::{read_entries, Ledger, LedgerEntry, Reconciliation, Totals};
+    use crate::{
+        client::DetailedGroupInfo,
+        fake::{group_info, id},
+        hook::ClaimOutcome,
+        portfolio::Holding,
+    };
+
+    fn holding(group_id: u64, owner: u64) -> Holding {
+        Holding {
+            group: DetailedGroupInfo {
+                owner: Some(id(owner)),
+                ..group_info(group_id, "", 1)
+            },
+            owned: owner == 1,
+            funds: (owner == 1).then_some(10),
+        }
+    }
+
+    #[test]
+    fn reconciliation_flags_lost_transferred_and_unrecorded_groups() {
+        let entries: Vec<_> = [
+            (1, ClaimOutcome::Kept),
+            (2, ClaimOutcome::Kept),
+            (3, ClaimOutcome::Kept),
+            (4, ClaimOutcome::Kept),
+            (4, ClaimOutcome::Pruned),
+            (5, ClaimOutcome::Left),
+            (7, ClaimOutcome::Recovered),
+            (8, ClaimOutcome::Recovered),
+        ]
+        .into_iter()
+        .map(|(group_id, outcome)| LedgerEntry::now(id(group_id), Some(10), outcome))
+        .collect();
+        // Group 2 was lost, group 3 taken over by user 2, groups 4 and 6 claimed elsewhere, and
+        // recovered group 8 lost since it was recorded
+        let holdings = [
+            holding(1, 1),
+            holding(3, 2),
+            holding(4, 1),
+            holding(6, 1),
+            holding(7, 1),
+        ];
+
+        let reconciliation = Reconciliation::new(&entries, &holdings);
+        assert_eq!(reconciliation.confirmed, 2);
+        assert_eq!(reconciliation.lost, [id(2), id(8)]);
+        assert_eq!(reconciliation.transferred, [(id(3), Some(id(2)))]);
+        assert_eq!(
+            reconciliation
+                .unrecorded
+                .iter()
+                .map(|holding| holding.group.id)
+                .collect::<Vec<_>>(),
+            [id(4), id(6)]
+        );
+    }
+
+    #[test]
+    fn totals_survive_reopening_and_torn_lines() {
+        let path = env::temp_dir().join(format!("ledger_{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let ledger = Ledger::open(&path).unwrap();
+        assert_eq!(ledger.previous(), Totals::default());
+        for (group_id, funds, outcome) in [
+            (1, Some(5_000_000_000), ClaimOutcome::Kept),
+            (2, Some(3), ClaimOutcome::Left),
+            (3, None, ClaimOutcome::Kept),
+            (5, Some(40), ClaimOutcome::Recovered),
+        ] {
+            ledger
+                .record(&LedgerEntry::now(id(group_id), funds, outcome))
+                .unwrap();
+        }
+        drop(ledger);
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(br#"{"group_id":4,"fun"#)
+            .unwrap();
+
+        let entries = read_entries(&path).unwrap();
+        let previous = Ledger::open(&path).unwrap().previous();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(
+            previous,
+            Totals {
+                groups: 2,
+                robux: 5_000_000_000
+            }
+        );
+    }
+}
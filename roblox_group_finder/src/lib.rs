@@ -0,0 +1,41 @@
+//! Finds Roblox groups without an owner and claims them.
+//!
+//! [`Finder`] wires the batch, detailed and claim workers in [`threads`] together, and reports
+//! what it does through [`FinderEvent`]s. The config loader, client traits and
+//! [`utils::GroupsApiExt`] can also be used on their own.
+
+#![deny(clippy::all, clippy::pedantic, clippy::nursery)]
+#![allow(
+    clippy::missing_panics_doc,
+    clippy::missing_errors_doc,
+    clippy::module_name_repetitions,
+    clippy::unreadable_literal
+)]
+
+pub mod catalog;
+pub mod claim_queue;
+pub mod client;
+pub mod config;
+pub mod constants;
+pub mod context;
+pub mod events;
+#[cfg(test)]
+mod fake;
+pub mod finder;
+pub mod hook;
+pub mod init;
+pub mod latency;
+pub mod ledger;
+pub mod policy;
+pub mod portfolio;
+pub mod scheduler;
+#[cfg(test)]
+mod simulation;
+pub mod status_display;
+pub mod threads;
+pub mod utils;
+pub mod webhook;
+
+pub use events::{EventSink, FinderEvent};
+pub use finder::{Finder, FinderBuilder};
+pub use policy::ClaimPolicy;
@@ -6,58 +6,154 @@
     clippy::unreadable_literal
 )]
 
-mod config;
-mod constants;
-mod init;
-mod status_display;
-mod threads;
-mod utils;
-
 use anyhow::{bail, Context, Result};
-use config::Settings;
 use indicatif::{ProgressBar, ProgressStyle};
-use once_cell::sync::OnceCell;
 use roblox_api::{
-    apis::{groups::GroupsApi, users::UsersAuthenticatedApi},
-    clients::{ClientBuilder, CookieClient},
+    apis::Id,
+    clients::{Client, ClientBuilder, CookieClient},
 };
-use tokio::task;
 use tracing::info;
 
-use utils::GroupsApiExt;
-
-use crate::{constants::BROWSER_ID_COOKIE_NAME, status_display::LogWriter};
-
-static SETTINGS: OnceCell<Settings> = OnceCell::new();
+use roblox_group_finder::{
+    client::{http_client, ClaimClient},
+    config::{self, Command, IdKind, Settings},
+    constants::BROWSER_ID_COOKIE_NAME,
+    init, portfolio,
+    status_display::LogWriter,
+    utils::{
+        self, AssetIdProbe, BadgeIdProbe, GroupIdProbe, GroupsApiExt, LatestIdProbe,
+        LatestIdSearch, PlaceIdProbe, UserIdProbe,
+    },
+    Finder,
+};
 
-#[tokio::main]
-#[allow(clippy::cast_possible_truncation, unused_must_use)]
-async fn main() -> Result<()> {
-    let config = config::get_config()?;
-    SETTINGS.set(config.settings).unwrap();
-    let settings = SETTINGS.get().unwrap();
+async fn search_latest_id<P: LatestIdProbe + Sync>(
+    probe: &P,
+    hint: Option<u64>,
+    max_gap: usize,
+) -> Result<LatestIdSearch>
+where
+    P::Error: std::error::Error + Sync + 'static,
+{
+    match hint.and_then(Id::new) {
+        Some(hint) => utils::find_latest_id_from(probe, hint, max_gap).await,
+        None => utils::find_latest_id(probe, max_gap).await,
+    }
+    .with_context(|| "Failed to get latest id")
+}
 
-    if config.proxies.is_empty() {
-        bail!("No proxies provided");
+async fn print_latest_id(kind: IdKind, hint: Option<u64>, settings: &Settings) -> Result<()> {
+    let max_gap = settings.latest_max_gap;
+    let http = http_client(settings);
+    let search = match kind {
+        IdKind::Group => {
+            let client = Client::new(
+                ClientBuilder::new()
+                    .no_proxy()
+                    .connect_timeout(settings.connect_timeout)
+                    .timeout(settings.timeout)
+                    .http2_prior_knowledge(),
+            );
+            search_latest_id(&GroupIdProbe(&client), hint, max_gap).await?
+        }
+        IdKind::User => search_latest_id(&UserIdProbe(&http), hint, max_gap).await?,
+        IdKind::Place => {
+            if settings.cookie.is_empty() {
+                bail!("Looking up places needs an account cookie");
+            }
+            let probe = PlaceIdProbe {
+                http: &http,
+                cookie: &settings.cookie,
+            };
+            search_latest_id(&probe, hint, max_gap).await?
+        }
+        IdKind::Asset => search_latest_id(&AssetIdProbe(&http), hint, max_gap).await?,
+        IdKind::Badge => search_latest_id(&BadgeIdProbe(&http), hint, max_gap).await?,
     };
+    println!(
+        "Latest id: {} (found in {} requests)",
+        search.id, search.requests
+    );
+    Ok(())
+}
+
+fn auth_client(settings: &Settings) -> CookieClient {
     let auth_client = CookieClient::new(
         ClientBuilder::new().no_proxy().http2_prior_knowledge(),
         &settings.cookie,
     );
     auth_client.insert_cookie(BROWSER_ID_COOKIE_NAME, &settings.browser_id);
+    auth_client
+}
 
-    let metadata = auth_client
-        .get_metadata()
+async fn print_portfolio(settings: &Settings) -> Result<()> {
+    let client = auth_client(settings);
+    let user_id = client
+        .user_id()
         .await
-        .with_context(|| "Failed to get group metadata")?;
-    if metadata.group_limit == 0 {
-        bail!("Auth cookie provided is invalid");
-    }
-    let user_id = auth_client
-        .get_authenticated_user()
+        .with_context(|| "Failed to get account's user ID")?;
+    let holdings = portfolio::holdings(&client, &http_client(settings), user_id)
         .await
-        .with_context(|| "Failed to get account's user ID")?
-        .id;
+        .with_context(|| "Failed to get the account's groups")?;
+    println!(
+        "{:>12}  {:<5}  {:>10}  {:>8}  Name",
+        "Group", "Owned", "Robux", "Members"
+    );
+    for holding in &holdings {
+        println!(
+            "{:>12}  {:<5}  {:>10}  {:>8}  {}",
+            holding.group.id,
+            if holding.owned { "yes" } else { "no" },
+            holding
+                .funds
+                .map_or_else(|| "-".to_owned(), |funds| funds.to_string()),
+            holding.group.member_count,
+            holding.group.name
+        );
+    }
+    println!(
+        "{} groups, {} owned, {} robux",
+        holdings.len(),
+        holdings.iter().filter(|holding| holding.owned).count(),
+        holdings
+            .iter()
+            .filter_map(|holding| holding.funds)
+            .sum::<u64>()
+    );
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn shutdown_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut terminate = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate.recv() => {}
+    }
+}
+
+#[cfg(not(unix))]
+async fn shutdown_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let config = config::get_config()?;
+    match config.command {
+        Some(Command::LatestId { kind, hint }) => {
+            return print_latest_id(kind, hint, &config.settings).await;
+        }
+        Some(Command::Portfolio) => return print_portfolio(&config.settings).await,
+        None => {}
+    }
+    let settings = config.settings;
+
+    if config.proxies.is_empty() {
+        bail!("No proxies provided");
+    };
+    let auth_client = auth_client(&settings);
 
     let bar = ProgressBar::new(0).with_style(ProgressStyle::with_template("{msg}").unwrap());
     let cloned_bar = bar.clone();
@@ -65,25 +161,38 @@ async fn main() -> Result<()> {
         .with_writer(move || LogWriter::new(cloned_bar.clone()))
         .init();
 
-    let claim_receiver = init::init_check_threads(
+    let latest_group_id = if let Some(hint) = settings.latest_hint.and_then(Id::new) {
+        let search = auth_client
+            .get_latest_group_id_from(hint, settings.latest_max_gap)
+            .await
+            .with_context(|| "Failed to get latest group id")?;
+        info!(
+            "Found latest group id {} in {} requests",
+            search.id, search.requests
+        );
+        search.id
+    } else {
         auth_client
-            .get_latest_group_id()
+            .get_latest_group_id(settings.latest_max_gap)
             .await
             .with_context(|| "Failed to get latest group id")?
-            .get() as usize,
-        &bar,
-        &config.proxies,
-        metadata.group_limit,
-    );
+    };
 
-    info!("Starting claim task");
-    task::spawn(threads::claim(
-        auth_client,
-        claim_receiver.to_async(),
-        metadata,
-        user_id,
-    ))
-    .await;
+    let check_clients = init::proxy_clients(&config.proxies, &settings);
+    #[allow(clippy::cast_possible_truncation)]
+    let finder = Finder::builder(settings)
+        .check_clients(check_clients)
+        .claim_client(auth_client)
+        .latest_group_id(latest_group_id.get() as usize)
+        .status_bar(bar)
+        .build()?;
 
-    Ok(())
+    // Stop cleanly on a signal so the summary is printed and sinks see the Stopped event
+    let shutdown = finder.context().shutdown.clone();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        info!("Received a shutdown signal, stopping");
+        shutdown.cancel();
+    });
+    finder.run().await
 }
@@ -0,0 +1,252 @@
+use std::fmt::Debug;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::client::DetailedGroupInfo;
+
+#[cfg(feature = "scripting")]
+mod script;
+#[cfg(feature = "scripting")]
+pub use script::ScriptPolicy;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreClaimDecision {
+    Claim,
+    Skip,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostClaimDecision {
+    Keep,
+    Leave,
+}
+
+/// Decides which ownerless groups get claimed and which claimed groups are kept.
+pub trait ClaimPolicy: Debug + Send + Sync {
+    /// Called with a public ownerless group's details before joining it.
+    fn before_claim(&self, group: &DetailedGroupInfo) -> PreClaimDecision;
+
+    /// Called once a group is claimed and its funds are known.
+    fn after_claim(&self, group: &DetailedGroupInfo, funds: u64) -> PostClaimDecision;
+}
+
+impl<P: ClaimPolicy + ?Sized> ClaimPolicy for Box<P> {
+    fn before_claim(&self, group: &DetailedGroupInfo) -> PreClaimDecision {
+        (**self).before_claim(group)
+    }
+
+    fn after_claim(&self, group: &DetailedGroupInfo, funds: u64) -> PostClaimDecision {
+        (**self).after_claim(group, funds)
+    }
+}
+
+/// Claims every group, then leaves the ones with less than the threshold in funds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FundsThreshold(pub u64);
+
+impl ClaimPolicy for FundsThreshold {
+    fn before_claim(&self, _group: &DetailedGroupInfo) -> PreClaimDecision {
+        PreClaimDecision::Claim
+    }
+
+    fn after_claim(&self, _group: &DetailedGroupInfo, funds: u64) -> PostClaimDecision {
+        if funds < self.0 {
+            PostClaimDecision::Leave
+        } else {
+            PostClaimDecision::Keep
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleAction {
+    Claim,
+    Skip,
+    Keep,
+    Leave,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub min_members: Option<u64>,
+    pub max_members: Option<u64>,
+    pub name: Option<Regex>,
+    pub description: Option<Regex>,
+    pub min_funds: Option<u64>,
+    pub action: RuleAction,
+}
+
+impl Rule {
+    #[must_use]
+    pub const fn new(action: RuleAction) -> Self {
+        Self {
+            min_members: None,
+            max_members: None,
+            name: None,
+            description: None,
+            min_funds: None,
+            action,
+        }
+    }
+
+    fn matches_group(&self, group: &DetailedGroupInfo) -> bool {
+        self.min_members
+            .is_none_or(|min_members| group.member_count >= min_members)
+            && self
+                .max_members
+                .is_none_or(|max_members| group.member_count <= max_members)
+            && self
+                .name
+                .as_ref()
+                .is_none_or(|name| name.is_match(&group.name))
+            && self
+                .description
+                .as_ref()
+                .is_none_or(|description| description.is_match(&group.description))
+    }
+}
+
+/// Applies the first matching rule, or the fallback policy when none match.
+///
+/// Claim and skip rules are checked before claiming, where funds aren't known yet, so rules
+/// with a funds condition only apply to keep and leave decisions.
+#[derive(Debug)]
+pub struct RulePolicy<P> {
+    pub rules: Vec<Rule>,
+    pub fallback: P,
+}
+
+impl<P: ClaimPolicy> ClaimPolicy for RulePolicy<P> {
+    fn before_claim(&self, group: &DetailedGroupInfo) -> PreClaimDecision {
+        self.rules
+            .iter()
+            .filter(|rule| rule.min_funds.is_none() && rule.matches_group(group))
+            .find_map(|rule| match rule.action {
+                RuleAction::Claim => Some(PreClaimDecision::Claim),
+                RuleAction::Skip => Some(PreClaimDecision::Skip),
+                RuleAction::Keep | RuleAction::Leave => None,
+            })
+            .unwrap_or_else(|| self.fallback.before_claim(group))
+    }
+
+    fn after_claim(&self, group: &DetailedGroupInfo, funds: u64) -> PostClaimDecision {
+        self.rules
+            .iter()
+            .filter(|rule| {
+                rule.min_funds.is_none_or(|min_funds| funds >= min_funds)
+                    && rule.matches_group(group)
+            })
+            .find_map(|rule| match rule.action {
+                RuleAction::Keep => Some(PostClaimDecision::Keep),
+                RuleAction::Leave => Some(PostClaimDecision::Leave),
+                RuleAction::Claim | RuleAction::Skip => None,
+            })
+            .unwrap_or_else(|| self.fallback.after_claim(group, funds))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::{
+        ClaimPolicy, FundsThreshold, PostClaimDecision, PreClaimDecision, Rule, RuleAction,
+        RulePolicy,
+    };
+    use crate::{config::RuleConfig, fake::group_info};
+
+    #[test]
+    fn funds_threshold_leaves_poor_groups() {
+        let policy = FundsThreshold(10);
+        assert_eq!(
+            policy.before_claim(&group_info(1, "", 0)),
+            PreClaimDecision::Claim
+        );
+        assert_eq!(
+            policy.after_claim(&group_info(1, "", 0), 9),
+            PostClaimDecision::Leave
+        );
+        assert_eq!(
+            policy.after_claim(&group_info(1, "", 0), 10),
+            PostClaimDecision::Keep
+        );
+    }
+
+    #[test]
+    fn funds_rules_only_decide_after_claiming() {
+        let rule = |action| RuleConfig {
+            min_members: None,
+            max_members: None,
+            name: None,
+            description: None,
+            min_funds: Some(100),
+            action,
+        };
+        assert!(rule(RuleAction::Claim).compile().is_err());
+        assert!(rule(RuleAction::Skip).compile().is_err());
+        assert!(rule(RuleAction::Keep).compile().is_ok());
+    }
+
+    #[test]
+    fn rules_match_names() {
+        let policy = RulePolicy {
+            rules: vec![Rule {
+                name: Some(Regex::new("(?i)clan").unwrap()),
+                ..Rule::new(RuleAction::Skip)
+            }],
+            fallback: FundsThreshold(10),
+        };
+        assert_eq!(
+            policy.before_claim(&group_info(1, "Best Clan", 0)),
+            PreClaimDecision::Skip
+        );
+        assert_eq!(
+            policy.before_claim(&group_info(1, "Best Group", 0)),
+            PreClaimDecision::Claim
+        );
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let policy = RulePolicy {
+            rules: vec![
+                Rule {
+                    max_members: Some(5),
+                    ..Rule::new(RuleAction::Skip)
+                },
+                Rule {
+                    min_funds: Some(1000),
+                    ..Rule::new(RuleAction::Keep)
+                },
+                Rule {
+                    min_members: Some(100),
+                    ..Rule::new(RuleAction::Keep)
+                },
+                Rule::new(RuleAction::Leave),
+            ],
+            fallback: FundsThreshold(10),
+        };
+        assert_eq!(
+            policy.before_claim(&group_info(1, "", 5)),
+            PreClaimDecision::Skip
+        );
+        assert_eq!(
+            policy.before_claim(&group_info(1, "", 6)),
+            PreClaimDecision::Claim
+        );
+        assert_eq!(
+            policy.after_claim(&group_info(1, "", 6), 1000),
+            PostClaimDecision::Keep
+        );
+        assert_eq!(
+            policy.after_claim(&group_info(1, "", 100), 0),
+            PostClaimDecision::Keep
+        );
+        assert_eq!(
+            policy.after_claim(&group_info(1, "", 99), 999),
+            PostClaimDecision::Leave
+        );
+    }
+}
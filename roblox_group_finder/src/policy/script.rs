@@ -0,0 +1,204 @@
+use std::{fs, path::Path, time::UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context, Result};
+use rhai::{Dynamic, Engine, ImmutableString, Map, Scope, AST};
+use tracing::warn;
+
+use super::{ClaimPolicy, PostClaimDecision, PreClaimDecision};
+use crate::client::DetailedGroupInfo;
+
+// Keeps a runaway script from stalling the worker that called it
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// Asks a Rhai script for claim decisions, and falls back to another policy when the script
+/// doesn't define the hook or fails.
+///
+/// The script can define `before_claim(group)`, returning `"claim"` or `"skip"`, and
+/// `after_claim(group, funds)`, returning `"keep"` or `"leave"`. `group` is a map with the
+/// fields of [`DetailedGroupInfo`].
+#[derive(Debug)]
+pub struct ScriptPolicy<P> {
+    engine: Engine,
+    ast: AST,
+    fallback: P,
+}
+
+impl<P: ClaimPolicy> ScriptPolicy<P> {
+    pub fn new(script: &str, fallback: P) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let ast = engine
+            .compile(script)
+            .map_err(|error| anyhow!("{error}"))
+            .with_context(|| "Failed to compile claim script")?;
+        Ok(Self {
+            engine,
+            ast,
+            fallback,
+        })
+    }
+
+    pub fn load(path: &Path, fallback: P) -> Result<Self> {
+        let script = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read claim script at {}", path.display()))?;
+        Self::new(&script, fallback)
+    }
+
+    fn call<T>(
+        &self,
+        hook: &str,
+        args: impl rhai::FuncArgs,
+        parse: impl FnOnce(&str) -> Option<T>,
+    ) -> Option<T> {
+        if !self
+            .ast
+            .iter_functions()
+            .any(|function| function.name == hook)
+        {
+            return None;
+        }
+        let result = self
+            .engine
+            .call_fn::<ImmutableString>(&mut Scope::new(), &self.ast, hook, args)
+            .map_err(|error| anyhow!("{error}"))
+            .and_then(|decision| match parse(&decision) {
+                Some(decision) => Ok(decision),
+                None => bail!("unknown decision \"{decision}\""),
+            });
+        match result {
+            Ok(decision) => Some(decision),
+            Err(error) => {
+                warn!("Claim script {hook} failed, using the default policy: {error}");
+                None
+            }
+        }
+    }
+}
+
+impl<P: ClaimPolicy> ClaimPolicy for ScriptPolicy<P> {
+    fn before_claim(&self, group: &DetailedGroupInfo) -> PreClaimDecision {
+        self.call(
+            "before_claim",
+            (group_map(group),),
+            |decision| match decision {
+                "claim" => Some(PreClaimDecision::Claim),
+                "skip" => Some(PreClaimDecision::Skip),
+                _ => None,
+            },
+        )
+        .unwrap_or_else(|| self.fallback.before_claim(group))
+    }
+
+    fn after_claim(&self, group: &DetailedGroupInfo, funds: u64) -> PostClaimDecision {
+        self.call(
+            "after_claim",
+            (group_map(group), int(funds)),
+            |decision| match decision {
+                "keep" => Some(PostClaimDecision::Keep),
+                "leave" => Some(PostClaimDecision::Leave),
+                _ => None,
+            },
+        )
+        .unwrap_or_else(|| self.fallback.after_claim(group, funds))
+    }
+}
+
+fn int(value: u64) -> Dynamic {
+    rhai::INT::try_from(value).unwrap_or(rhai::INT::MAX).into()
+}
+
+fn group_map(group: &DetailedGroupInfo) -> Map {
+    let mut map = Map::new();
+    map.insert("id".into(), int(group.id.get()));
+    map.insert(
+        "owner".into(),
+        group.owner.map_or(Dynamic::UNIT, |owner| int(owner.get())),
+    );
+    map.insert(
+        "public_entry_allowed".into(),
+        group.public_entry_allowed.into(),
+    );
+    map.insert("is_locked".into(), group.is_locked.into());
+    map.insert("name".into(), group.name.clone().into());
+    map.insert("description".into(), group.description.clone().into());
+    map.insert("member_count".into(), int(group.member_count));
+    map.insert(
+        "created".into(),
+        group
+            .created
+            .and_then(|created| created.duration_since(UNIX_EPOCH).ok())
+            .map_or(Dynamic::UNIT, |created| int(created.as_secs())),
+    );
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScriptPolicy;
+    use crate::{
+        fake::group_info,
+        policy::{ClaimPolicy, FundsThreshold, PostClaimDecision, PreClaimDecision},
+    };
+
+    #[test]
+    fn script_decides() {
+        let policy = ScriptPolicy::new(
+            r#"
+            fn before_claim(group) {
+                if group.name.contains("Clan") { "skip" } else { "claim" }
+            }
+            fn after_claim(group, funds) {
+                if funds >= 5 || group.member_count > 100 { "keep" } else { "leave" }
+            }
+            "#,
+            FundsThreshold(1000),
+        )
+        .unwrap();
+        assert_eq!(
+            policy.before_claim(&group_info(1, "Best Clan", 0)),
+            PreClaimDecision::Skip
+        );
+        assert_eq!(
+            policy.before_claim(&group_info(1, "Best", 0)),
+            PreClaimDecision::Claim
+        );
+        assert_eq!(
+            policy.after_claim(&group_info(1, "Best", 0), 5),
+            PostClaimDecision::Keep
+        );
+        assert_eq!(
+            policy.after_claim(&group_info(1, "Best", 200), 0),
+            PostClaimDecision::Keep
+        );
+        assert_eq!(
+            policy.after_claim(&group_info(1, "Best", 0), 4),
+            PostClaimDecision::Leave
+        );
+    }
+
+    #[test]
+    fn script_errors_fall_back() {
+        let policy = ScriptPolicy::new(
+            r#"
+            fn after_claim(group, funds) {
+                if funds > 50 { "maybe" } else { group.missing.len() }
+            }
+            "#,
+            FundsThreshold(10),
+        )
+        .unwrap();
+        assert_eq!(
+            policy.before_claim(&group_info(1, "", 0)),
+            PreClaimDecision::Claim
+        );
+        assert_eq!(
+            policy.after_claim(&group_info(1, "", 0), 100),
+            PostClaimDecision::Keep
+        );
+        assert_eq!(
+            policy.after_claim(&group_info(1, "", 0), 5),
+            PostClaimDecision::Leave
+        );
+        assert!(ScriptPolicy::new("fn broken(", FundsThreshold(10)).is_err());
+    }
+}
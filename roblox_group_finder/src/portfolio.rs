@@ -0,0 +1,123 @@
+use roblox_api::apis::Id;
+use tracing::warn;
+
+use crate::client::{ApiResult, ClaimClient, DetailedGroupInfo, WebClient};
+
+/// A group the account is a member of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Holding {
+    pub group: DetailedGroupInfo,
+    pub owned: bool,
+    /// Only fetched for owned groups, and `None` if that failed.
+    pub funds: Option<u64>,
+}
+
+pub async fn holdings(
+    client: &impl ClaimClient,
+    web: &(impl WebClient + ?Sized),
+    user_id: Id,
+) -> ApiResult<Vec<Holding>> {
+    let mut holdings = Vec::new();
+    for group in web.memberships(user_id).await? {
+        let owned = group.owner == Some(user_id);
+        let funds = if owned {
+            client
+                .funds(group.id)
+                .await
+                .inspect_err(|error| {
+                    warn!(
+                        "Failed to get funds for group {}, error: {:?}",
+                        group.id, error
+                    );
+                })
+                .ok()
+        } else {
+            None
+        };
+        holdings.push(Holding {
+            group,
+            owned,
+            funds,
+        });
+    }
+    Ok(holdings)
+}
+
+/// The owned group with the least funds, then the fewest members, out of those with at most
+/// `max_funds` that `prunable` accepts.
+///
+/// Groups whose funds couldn't be fetched are never picked, since they might be worth keeping.
+#[must_use]
+pub fn lowest_value(
+    holdings: &[Holding],
+    max_funds: u64,
+    prunable: impl Fn(Id) -> bool,
+) -> Option<&Holding> {
+    holdings
+        .iter()
+        .filter(|holding| {
+            holding.owned
+                && holding.funds.is_some_and(|funds| funds <= max_funds)
+                && prunable(holding.group.id)
+        })
+        .min_by_key(|holding| (holding.funds, holding.group.member_count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{holdings, lowest_value};
+    use crate::fake::{id, FakeGroup, FakeRoblox};
+
+    #[tokio::test]
+    async fn lowest_value_skips_groups_the_account_does_not_own() {
+        let fake = FakeRoblox::new(id(1), 100);
+        for (group_id, owner, funds) in [
+            (1, Some(id(1)), 50),
+            (2, Some(id(2)), 0),
+            (3, Some(id(1)), 5),
+        ] {
+            fake.insert_group(
+                id(group_id),
+                FakeGroup {
+                    owner,
+                    funds,
+                    members: std::iter::once(fake.user_id).collect(),
+                    ..FakeGroup::default()
+                },
+            );
+        }
+        fake.insert_group(id(4), FakeGroup::default());
+
+        let holdings = holdings(&fake, &fake, fake.user_id).await.unwrap();
+        let funds: Vec<_> = holdings
+            .iter()
+            .map(|holding| (holding.group.id, holding.owned, holding.funds))
+            .collect();
+        assert_eq!(
+            funds,
+            [
+                (id(1), true, Some(50)),
+                (id(2), false, None),
+                (id(3), true, Some(5))
+            ]
+        );
+        assert_eq!(
+            lowest_value(&holdings, u64::MAX, |_| true)
+                .unwrap()
+                .group
+                .id,
+            id(3)
+        );
+        assert_eq!(
+            lowest_value(&holdings, 50, |group_id| group_id != id(3))
+                .unwrap()
+                .group
+                .id,
+            id(1)
+        );
+        assert_eq!(
+            lowest_value(&holdings, 49, |group_id| group_id != id(3)),
+            None
+        );
+    }
+}
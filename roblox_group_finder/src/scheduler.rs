@@ -0,0 +1,194 @@
+use std::{cmp::Ordering, collections::BinaryHeap, sync::Mutex, time::Duration};
+
+use fxhash::FxHashMap;
+use roblox_api::apis::Id;
+use tokio::time::Instant;
+
+use crate::{
+    constants::{CLAIM_RETRY_WAIT, OWNERSHIP_AGE_RECHECK_DIVISOR},
+    threads::{GroupState, TrackedGroup},
+};
+
+#[derive(Debug)]
+pub struct ScheduledGroup {
+    pub next_check: Instant,
+    pub group: TrackedGroup,
+}
+impl PartialEq for ScheduledGroup {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl Eq for ScheduledGroup {}
+impl PartialOrd for ScheduledGroup {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for ScheduledGroup {
+    // Reversed so that the max-heap pops the earliest (then lowest id) group first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .next_check
+            .cmp(&self.next_check)
+            .then_with(|| other.group.id.cmp(&self.group.id))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Schedule {
+    queue: BinaryHeap<ScheduledGroup>,
+    // The latest check time handed out, which runs ahead of real time while groups are
+    // checked before they are due
+    clock: Option<Instant>,
+    // Only known for groups that went through a detailed check
+    member_counts: FxHashMap<Id, u64>,
+}
+
+/// Orders groups by when they should next be checked.
+///
+/// Groups are handed out in that order whether or not they are due yet, so the intervals only
+/// decide how often each group is checked relative to the others, and the proxies never wait.
+#[derive(Debug)]
+pub struct Scheduler {
+    schedule: Mutex<Schedule>,
+    min_recheck: Duration,
+    max_recheck: Duration,
+}
+
+impl Scheduler {
+    #[must_use]
+    pub fn new(min_recheck: Duration, max_recheck: Duration) -> Self {
+        Self {
+            schedule: Mutex::new(Schedule::default()),
+            min_recheck,
+            max_recheck,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.schedule.lock().unwrap().queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.schedule.lock().unwrap().queue.is_empty()
+    }
+
+    pub fn schedule(&self, group: TrackedGroup, next_check: Instant) {
+        self.schedule
+            .lock()
+            .unwrap()
+            .queue
+            .push(ScheduledGroup { next_check, group });
+    }
+
+    pub fn schedule_all(&self, groups: impl IntoIterator<Item = ScheduledGroup>) {
+        self.schedule.lock().unwrap().queue.extend(groups);
+    }
+
+    /// Pops up to `max` of the groups that should be checked soonest, even if they aren't due.
+    pub fn pop_next(&self, max: usize, mut on_pop: impl FnMut(ScheduledGroup)) {
+        let schedule = &mut *self.schedule.lock().unwrap();
+        for _ in 0..max {
+            let Some(scheduled) = schedule.queue.pop() else {
+                break;
+            };
+            schedule.clock = schedule.clock.max(Some(scheduled.next_check));
+            on_pop(scheduled);
+        }
+    }
+
+    /// Remembers a group's member count from a detailed check, to weigh its rechecks by.
+    pub fn record_member_count(&self, id: Id, member_count: u64) {
+        self.schedule
+            .lock()
+            .unwrap()
+            .member_counts
+            .insert(id, member_count);
+    }
+
+    pub fn reschedule(&self, group: TrackedGroup, now: Instant) {
+        let schedule = &mut *self.schedule.lock().unwrap();
+        // Intervals count from the schedule's clock, so groups checked early keep their order
+        let base = schedule.clock.map_or(now, |clock| clock.max(now));
+        let next_check = match group.state {
+            GroupState::Locked | GroupState::Deleted | GroupState::Claimed => {
+                schedule.member_counts.remove(&group.id);
+                return;
+            }
+            GroupState::Unseen | GroupState::Missing => now,
+            GroupState::Owned { since } | GroupState::Ownerless { since } => {
+                base + self.recheck_interval(
+                    now.saturating_duration_since(since),
+                    schedule.member_counts.get(&group.id).copied(),
+                )
+            }
+            GroupState::ClaimFailed { attempts, .. } => {
+                base + CLAIM_RETRY_WAIT
+                    .saturating_mul(1 << attempts.min(16))
+                    .clamp(self.min_recheck, self.max_recheck)
+            }
+        };
+        schedule.queue.push(ScheduledGroup { next_check, group });
+    }
+
+    // Groups whose ownership hasn't changed in a long time are unlikely to change soon, so they
+    // are rechecked less often than recently changed ones. Bigger groups are worth more once
+    // abandoned, so they are rechecked more often.
+    fn recheck_interval(&self, since_last_change: Duration, member_count: Option<u64>) -> Duration {
+        let size_weight = member_count.map_or(1, |count| 1 + count.checked_ilog2().unwrap_or(0));
+        (since_last_change / OWNERSHIP_AGE_RECHECK_DIVISOR / size_weight)
+            .clamp(self.min_recheck, self.max_recheck)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+
+    use super::Scheduler;
+    use crate::{
+        fake::id,
+        threads::{GroupState, TrackedGroup},
+    };
+
+    fn check_counts(scheduler: &Scheduler, checks: usize) -> [usize; 3] {
+        let mut counts = [0; 3];
+        let mut now = Instant::now();
+        for _ in 0..checks {
+            let mut popped = None;
+            scheduler.pop_next(1, |scheduled| popped = Some(scheduled.group));
+            let group = popped.unwrap();
+            counts[usize::try_from(group.id.get()).unwrap() - 1] += 1;
+            now += Duration::from_secs(1);
+            scheduler.reschedule(group, now);
+        }
+        counts
+    }
+
+    #[test]
+    fn recent_changes_and_big_groups_are_checked_more_often() {
+        let scheduler = Scheduler::new(Duration::ZERO, Duration::from_hours(1));
+        let now = Instant::now();
+        let long_ago = now.checked_sub(Duration::from_hours(8)).unwrap();
+        // Group 1 changed owner recently, group 3 has as old an owner as group 2 but more
+        // members
+        for (group_id, since) in [(1, now), (2, long_ago), (3, long_ago)] {
+            scheduler.reschedule(
+                TrackedGroup {
+                    id: id(group_id),
+                    state: GroupState::Owned { since },
+                },
+                now,
+            );
+        }
+        scheduler.record_member_count(id(3), 1000);
+
+        // No group is due for minutes, but one is handed out every second regardless
+        let [recent, old, big] = check_counts(&scheduler, 600);
+        assert!(recent > big && big > old, "{recent} {old} {big}");
+        assert!(old > 0);
+    }
+}
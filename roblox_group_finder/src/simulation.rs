@@ -0,0 +1,136 @@
+use std::{sync::Arc, time::Duration};
+
+use roblox_api::apis::{groups::Metadata, Id};
+use tokio::{
+    task,
+    time::{self, Instant},
+};
+
+use crate::{
+    claim_queue::ClaimQueue,
+    context::FinderContext,
+    fake::{id, settings, FakeCall, FakeGroup, FakeRoblox},
+    init::start_check_tasks,
+    threads,
+};
+
+const GROUP_COUNT: usize = 2000;
+const PROXY_COUNT: usize = 2;
+const RUN_TIME: Duration = Duration::from_mins(30);
+
+struct Abandonment {
+    at: Duration,
+    id: Id,
+    public_entry_allowed: bool,
+}
+
+struct Outcome {
+    latencies: Vec<Duration>,
+    claimed: usize,
+    batch_requests: usize,
+    detailed_requests: usize,
+}
+
+// Every group starts out owned, then one owner leaves per minute between minutes 5 and 25
+fn abandonments() -> Vec<Abandonment> {
+    (0..20)
+        .map(|minute| Abandonment {
+            at: Duration::from_secs(5 * 60 + minute * 60),
+            id: id((minute * 97) % GROUP_COUNT as u64 + 1),
+            public_entry_allowed: minute % 2 == 0,
+        })
+        .collect()
+}
+
+async fn simulate(abandonments: &[Abandonment]) -> Outcome {
+    let fake = FakeRoblox::new(id(1), 100);
+    let mut ctx = FinderContext::new(settings());
+    ctx.web = Box::new(fake.clone());
+    let ctx = Arc::new(ctx);
+    for group_id in 1..=GROUP_COUNT as u64 {
+        fake.insert_group(
+            id(group_id),
+            FakeGroup {
+                owner: Some(id(2)),
+                funds: 100,
+                ..FakeGroup::default()
+            },
+        );
+    }
+    let claim_queue = Arc::new(ClaimQueue::new(100));
+    start_check_tasks(
+        &ctx,
+        GROUP_COUNT,
+        vec![fake.clone(); PROXY_COUNT],
+        &claim_queue,
+        &[],
+    );
+    let claim_task = task::spawn(threads::claim(
+        fake.clone(),
+        ctx.clone(),
+        claim_queue,
+        Metadata {
+            group_limit: 100,
+            current_group_count: 0,
+        },
+        fake.user_id,
+    ));
+
+    let start = Instant::now();
+    let mut latencies = vec![None; abandonments.len()];
+    while start.elapsed() < RUN_TIME {
+        time::sleep(Duration::from_secs(1)).await;
+        let elapsed = start.elapsed();
+        for (abandonment, latency) in abandonments.iter().zip(&mut latencies) {
+            if abandonment.at > elapsed || latency.is_some() {
+                continue;
+            }
+            let group = fake.group(abandonment.id).unwrap();
+            if group.owner == Some(id(2)) {
+                fake.update_group(abandonment.id, |group| {
+                    group.owner = None;
+                    group.public_entry_allowed = abandonment.public_entry_allowed;
+                });
+            } else if group.owner == Some(fake.user_id) {
+                *latency = Some(elapsed.saturating_sub(abandonment.at));
+            }
+        }
+    }
+    ctx.shutdown.cancel();
+    claim_task.await.unwrap();
+
+    Outcome {
+        latencies: latencies.into_iter().flatten().collect(),
+        claimed: fake.groups_owned(),
+        batch_requests: fake.calls(FakeCall::BatchInfo),
+        detailed_requests: fake.calls(FakeCall::DetailedInfo),
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn abandoned_public_groups_are_claimed() {
+    let abandonments = abandonments();
+    let outcome = simulate(&abandonments).await;
+
+    let public = abandonments
+        .iter()
+        .filter(|abandonment| abandonment.public_entry_allowed)
+        .count();
+    assert_eq!(outcome.claimed, public);
+    assert_eq!(outcome.latencies.len(), public);
+    let worst = outcome.latencies.iter().max().unwrap();
+    assert!(
+        *worst <= Duration::from_secs(150),
+        "worst latency {worst:?}"
+    );
+    // Private groups are only rechecked through batches, so each abandonment costs one detailed request
+    assert_eq!(outcome.detailed_requests, abandonments.len());
+    // Old ownership only moves groups back in the schedule, the proxies still send a batch every
+    // batch_wait
+    let capacity = PROXY_COUNT * usize::try_from(RUN_TIME.as_secs()).unwrap();
+    assert!(
+        outcome.batch_requests >= capacity * 9 / 10,
+        "{} batch requests",
+        outcome.batch_requests
+    );
+}
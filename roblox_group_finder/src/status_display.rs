@@ -1,21 +1,14 @@
 use std::{
     io,
-    sync::atomic::{AtomicU16, AtomicU32, Ordering},
+    sync::{atomic::Ordering, Arc},
     time::Duration,
 };
 
 use indicatif::ProgressBar;
-use kanal::Sender;
 use simple_moving_average::{SingleSumSMA, SMA};
 use tokio::time;
 
-use crate::threads::TrackedGroup;
-
-pub static GROUPS_OWNED: AtomicU16 = AtomicU16::new(0);
-pub static GROUPS_CLAIMED: AtomicU16 = AtomicU16::new(0);
-pub static BATCH_CHECK_COUNTER: AtomicU32 = AtomicU32::new(0);
-pub static BATCH_PROXIES: AtomicU32 = AtomicU32::new(0);
-pub static ROBUX_CLAIMED: AtomicU32 = AtomicU32::new(0);
+use crate::context::FinderContext;
 
 pub struct LogWriter(ProgressBar);
 impl io::Write for LogWriter {
@@ -41,29 +34,38 @@ impl io::Write for LogWriter {
 }
 
 impl LogWriter {
+    #[must_use]
     pub const fn new(bar: ProgressBar) -> Self {
         Self(bar)
     }
 }
 
-pub async fn status_thread(
-    bar: ProgressBar,
-    group_limit: u16,
-    batch_senders: (Sender<TrackedGroup>, Sender<TrackedGroup>),
-) {
+pub async fn status_thread(bar: ProgressBar, ctx: Arc<FinderContext>) {
+    let counters = &ctx.counters;
     let mut batch: SingleSumSMA<u32, u32, 10> = SingleSumSMA::new();
-    loop {
-        batch.add_sample(BATCH_CHECK_COUNTER.swap(0, Ordering::Relaxed));
+    while !ctx.shutdown.is_cancelled() {
+        batch.add_sample(counters.batch_checks.swap(0, Ordering::Relaxed));
 
+        let (session, lifetime) = ctx.totals();
         bar.set_message(format!(
-            "Groups claimed: {}\nRobux claimed: {}\nCPM: {:.2}M\nGroup capacity: {}/{}\nProxies left: {}\nQueue size: {}",
-            GROUPS_CLAIMED.load(Ordering::Relaxed),
-            ROBUX_CLAIMED.load(Ordering::Relaxed),
+            "Groups claimed: {} ({} lifetime)\nRobux claimed: {} ({} lifetime)\nCPM: {:.2}M\nGroup capacity: {}/{}\nProxies left: {}\nQueue size: {}\nOwnerless on first sight: {}\nLocked groups skipped: {}\nDeleted groups skipped: {}\nClaims lost: {} ({:.1}% of attempts)\nClaim latency: {}",
+            session.groups,
+            lifetime.groups,
+            session.robux,
+            lifetime.robux,
             f64::from(batch.get_average()) * 60f64 / 1000000f64,
-            GROUPS_OWNED.load(Ordering::Relaxed),
-            group_limit,
-            BATCH_PROXIES.load(Ordering::Relaxed),
-            batch_senders.0.len() + batch_senders.1.len(),
+            counters.groups_owned.load(Ordering::Relaxed),
+            counters.group_limit.load(Ordering::Relaxed),
+            counters.batch_proxies.load(Ordering::Relaxed),
+            ctx.scheduler.len(),
+            ctx.catalog.len(),
+            counters.locked_groups.load(Ordering::Relaxed),
+            counters.deleted_groups.load(Ordering::Relaxed),
+            counters.claims_lost.load(Ordering::Relaxed),
+            counters.lost_race_rate().unwrap_or_default() * 100f64,
+            ctx.latency
+                .summary()
+                .map_or_else(|| "no claims yet".to_owned(), |summary| summary.to_string()),
         ));
 
         time::sleep(Duration::from_secs(1)).await;
@@ -1,16 +1,12 @@
-use std::{collections::HashMap, process, sync::atomic::Ordering};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{atomic::Ordering, Arc},
+};
 
 use fxhash::FxBuildHasher;
-use kanal::{AsyncReceiver, Receiver, Sender};
-use roblox_api::{
-    apis::Error,
-    apis::Id,
-    apis::{
-        economy::EconomyAuthenticatedApi,
-        groups::{GroupsApi, GroupsAuthenticatedApi, Metadata},
-    },
-    AuthenticatedClient, BaseClient,
-};
+use kanal::{Receiver, Sender};
+use roblox_api::apis::{groups::Metadata, Id};
 use tokio::{
     task,
     time::{self, Instant},
@@ -18,65 +14,176 @@ use tokio::{
 use tracing::{error, info, warn};
 
 use crate::{
-    constants::{CAPTCHA_MESSAGE, RATE_LIMITED_MESSAGE},
-    status_display::{
-        BATCH_CHECK_COUNTER, BATCH_PROXIES, GROUPS_CLAIMED, GROUPS_OWNED, ROBUX_CLAIMED,
+    claim_queue::ClaimQueue,
+    client::{ApiFailure, ApiResult, CheckClient, ClaimClient, DetailedGroupInfo},
+    config::OwnerlessPolicy,
+    constants::{
+        CAPACITY_RECONCILE_INTERVAL, CLAIM_STEP_RETRY_LIMIT, CLAIM_STEP_RETRY_WAIT, IDLE_WAIT,
+        MAX_IDS_IN_BATCH_REQUEST, PRUNE_MARGIN,
     },
-    SETTINGS,
+    context::FinderContext,
+    events::FinderEvent,
+    hook::ClaimOutcome,
+    latency::{DetectionTimes, StageLatencies},
+    policy::{PostClaimDecision, PreClaimDecision},
+    portfolio,
+    scheduler::ScheduledGroup,
 };
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClaimFailureReason {
+    Join,
+    Claim,
+    Lost,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupState {
+    Unseen,
+    Owned {
+        since: Instant,
+    },
+    Ownerless {
+        since: Instant,
+    },
+    Locked,
+    // Left out of one batch response, a second miss marks the group deleted
+    Missing,
+    Deleted,
+    Claimed,
+    ClaimFailed {
+        reason: ClaimFailureReason,
+        attempts: usize,
+    },
+}
+
 #[derive(Debug)]
 pub struct TrackedGroup {
     pub id: Id,
-    pub processed_before: bool,
+    pub state: GroupState,
 }
 impl Default for TrackedGroup {
     fn default() -> Self {
         Self {
             id: Id::MIN,
-            processed_before: false,
+            state: GroupState::Unseen,
+        }
+    }
+}
+
+// A group the batch check saw without an owner, on its way to the detailed check
+#[derive(Debug)]
+pub struct Detection {
+    pub group: TrackedGroup,
+    pub observed: Instant,
+}
+
+#[derive(Debug)]
+pub struct ClaimCandidate {
+    pub group: TrackedGroup,
+    pub details: DetailedGroupInfo,
+    pub times: DetectionTimes,
+}
+
+// Returns `None` once the finder shuts down. Waiting on the receivers directly could lose a
+// group, as kanal drops what a cancelled receive future was handed.
+async fn next_detection(
+    ctx: &FinderContext,
+    check_receiver: &Receiver<Detection>,
+    priority_receiver: &Receiver<Detection>,
+) -> Option<Detection> {
+    loop {
+        let ready = ctx.detection_ready.notified();
+        if let Some(detection) = priority_receiver.try_recv().unwrap() {
+            return Some(detection);
+        } else if let Some(detection) = check_receiver.try_recv().unwrap() {
+            return Some(detection);
+        }
+        tokio::select! {
+            () = ctx.shutdown.cancelled() => return None,
+            () = ready => {}
         }
     }
 }
 
 #[allow(unused_must_use)]
 pub async fn detailed_check(
-    client: impl BaseClient + Send,
-    check_receiver: Receiver<Id>,
-    priority_check_queue: (Sender<Id>, Receiver<Id>),
-    claim_sender: Sender<Id>,
+    client: impl CheckClient,
+    ctx: Arc<FinderContext>,
+    check_receiver: Receiver<Detection>,
+    priority_check_queue: (Sender<Detection>, Receiver<Detection>),
+    claim_queue: Arc<ClaimQueue>,
 ) {
     let mut retry_count: usize = 0;
-    let settings = SETTINGS.get().unwrap();
-    loop {
-        let current_group = loop {
-            if let Some(group) = priority_check_queue.1.try_recv().unwrap() {
-                break group;
-            } else if let Some(group) = check_receiver.try_recv().unwrap() {
-                break group;
-            }
-            task::yield_now().await;
+    let settings = &ctx.settings;
+    while !ctx.shutdown.is_cancelled() {
+        let Some(Detection {
+            group: mut current_group,
+            observed,
+        }) = next_detection(&ctx, &check_receiver, &priority_check_queue.1).await
+        else {
+            return;
         };
 
         let request_start = Instant::now();
-        let response = client.get_detailed_info(current_group).await;
+        let response = client.detailed_info(current_group.id).await;
         let request_end = Instant::now();
         match response {
             Ok(group_info) => {
                 retry_count = 0;
-                if !claim_sender.is_full()
-                    && group_info.public_entry_allowed
-                    && group_info.owner.is_none()
-                {
-                    claim_sender.send(current_group);
+                ctx.scheduler
+                    .record_member_count(current_group.id, group_info.member_count);
+                if group_info.is_locked {
+                    ctx.counters.locked_groups.fetch_add(1, Ordering::Relaxed);
+                    current_group.state = GroupState::Locked;
+                    ctx.scheduler.reschedule(current_group, request_end);
+                } else if group_info.owner.is_some() {
+                    let state = match current_group.state {
+                        owned @ GroupState::Owned { .. } => owned,
+                        _ => GroupState::Owned { since: request_end },
+                    };
+                    ctx.scheduler.reschedule(
+                        TrackedGroup {
+                            id: current_group.id,
+                            state,
+                        },
+                        request_end,
+                    );
+                } else if current_group.state == GroupState::Unseen {
+                    // Groups ownerless on first sight follow the ownerless policy
+                    route_ownerless_group(
+                        current_group,
+                        request_end,
+                        &ctx,
+                        &priority_check_queue.0,
+                    );
+                } else if group_info.public_entry_allowed {
+                    let times = DetectionTimes {
+                        observed,
+                        detailed: request_end,
+                    };
+                    offer_claim(&ctx, &claim_queue, current_group, group_info, times).await;
+                } else {
+                    ctx.scheduler.reschedule(current_group, request_end);
                 }
             }
+            Err(ApiFailure::InvalidGroup) => {
+                ctx.counters.deleted_groups.fetch_add(1, Ordering::Relaxed);
+                ctx.catalog.remove(current_group.id);
+                current_group.state = GroupState::Deleted;
+                ctx.scheduler.reschedule(current_group, request_end);
+            }
             Err(error) => {
-                priority_check_queue.0.send(current_group);
-                if let Error::Api(error) = error {
-                    if error.message == RATE_LIMITED_MESSAGE {
-                        continue;
-                    }
+                send_detection(
+                    &ctx,
+                    &priority_check_queue.0,
+                    Detection {
+                        group: current_group,
+                        observed,
+                    },
+                );
+                if error == ApiFailure::RateLimited {
+                    continue;
                 }
                 if retry_count >= settings.retry_limit {
                     break;
@@ -93,69 +200,167 @@ pub async fn detailed_check(
     }
 }
 
+// Queues a public group for claiming if the policy accepts it, after filling in the creation
+// date that the policy and the claim queue's ranking can use
+async fn offer_claim(
+    ctx: &FinderContext,
+    claim_queue: &ClaimQueue,
+    group: TrackedGroup,
+    mut details: DetailedGroupInfo,
+    times: DetectionTimes,
+) {
+    details.created = ctx
+        .web
+        .created(group.id)
+        .await
+        .inspect_err(|error| {
+            warn!(
+                "Failed to get the creation date of group {}, error: {:?}",
+                group.id, error
+            );
+        })
+        .ok()
+        .flatten();
+    if ctx.policy.before_claim(&details) != PreClaimDecision::Claim {
+        ctx.scheduler.reschedule(group, times.detailed);
+        return;
+    }
+    let id = group.id;
+    // Another task may have queued the group already
+    if claim_queue.push(ClaimCandidate {
+        group,
+        details,
+        times,
+    }) {
+        ctx.emit(FinderEvent::Detected { id });
+    }
+}
+
+#[allow(unused_must_use)]
+fn send_detection(ctx: &FinderContext, sender: &Sender<Detection>, detection: Detection) {
+    sender.send(detection);
+    ctx.detection_ready.notify_one();
+}
+
+fn route_ownerless_group(
+    mut group: TrackedGroup,
+    now: Instant,
+    ctx: &FinderContext,
+    detailed_check_sender: &Sender<Detection>,
+) {
+    let (settings, scheduler, catalog) = (&ctx.settings, &ctx.scheduler, &ctx.catalog);
+    let detect = |group| {
+        send_detection(
+            ctx,
+            detailed_check_sender,
+            Detection {
+                group,
+                observed: now,
+            },
+        );
+    };
+    match group.state {
+        GroupState::Owned { .. } => {
+            group.state = GroupState::Ownerless { since: now };
+            detect(group);
+        }
+        GroupState::ClaimFailed { attempts, .. } if attempts < settings.retry_limit => {
+            detect(group);
+        }
+        GroupState::Unseen => {
+            catalog.insert(group.id);
+            group.state = GroupState::Ownerless { since: now };
+            match settings.ownerless_policy {
+                OwnerlessPolicy::Ignore => {}
+                OwnerlessPolicy::Recheck => scheduler.reschedule(group, now),
+                OwnerlessPolicy::DetailedOnce => detect(group),
+            }
+        }
+        GroupState::Ownerless { .. }
+            if settings.ownerless_policy == OwnerlessPolicy::Recheck
+                && catalog.contains(group.id) =>
+        {
+            detect(group);
+        }
+        _ => scheduler.reschedule(group, now),
+    }
+}
+
 #[allow(unused_must_use)]
 pub async fn batch_check(
-    client: impl BaseClient + Send,
-    check_queue: (Sender<TrackedGroup>, Receiver<TrackedGroup>),
-    priority_check_queue: (Sender<TrackedGroup>, Receiver<TrackedGroup>),
-    detailed_check_sender: Sender<Id>,
+    client: impl CheckClient,
+    ctx: Arc<FinderContext>,
+    detailed_check_sender: Sender<Detection>,
 ) {
     let mut retry_count: usize = 0;
-    let settings = SETTINGS.get().unwrap();
-    let mut current_batch: HashMap<Id, bool, FxBuildHasher> =
-        HashMap::with_capacity_and_hasher(100, FxBuildHasher::default());
-    loop {
+    let (settings, scheduler, catalog) = (&ctx.settings, &ctx.scheduler, &ctx.catalog);
+    let mut current_batch: HashMap<Id, ScheduledGroup, FxBuildHasher> =
+        HashMap::with_capacity_and_hasher(MAX_IDS_IN_BATCH_REQUEST, FxBuildHasher::default());
+    while !ctx.shutdown.is_cancelled() {
         current_batch.clear();
-        while current_batch.len() < 100 {
-            let group = priority_check_queue
-                .1
-                .try_recv()
-                .unwrap()
-                .or_else(|| check_queue.1.try_recv().unwrap());
-            match group {
-                Some(group) => {
-                    current_batch.insert(group.id, group.processed_before);
-                }
-                None => {
-                    if !current_batch.is_empty() {
-                        break;
-                    }
-                }
+        loop {
+            if ctx.shutdown.is_cancelled() {
+                return;
+            }
+            scheduler.pop_next(MAX_IDS_IN_BATCH_REQUEST, |entry| {
+                current_batch.insert(entry.group.id, entry);
+            });
+            if !current_batch.is_empty() {
+                break;
             }
+            time::sleep(IDLE_WAIT).await;
         }
 
+        let ids: Vec<Id> = current_batch.keys().copied().collect();
         let request_start = Instant::now();
-        let response = client.get_batch_info(current_batch.keys().copied()).await;
+        let response = client.batch_info(&ids).await;
         let request_end = Instant::now();
         match response {
             Ok(data) => {
                 retry_count = 0;
                 for group_info in &data {
+                    let Some(entry) = current_batch.remove(&group_info.id) else {
+                        continue;
+                    };
+                    let mut group = entry.group;
+                    // What happened to the group while it was missing is unknown, so treat it
+                    // as a possible owner change
+                    if group.state == GroupState::Missing {
+                        group.state = GroupState::Owned { since: request_end };
+                    }
                     if group_info.owner.is_none() {
-                        if *current_batch.get(&group_info.id).unwrap() {
-                            detailed_check_sender.send(group_info.id);
+                        route_ownerless_group(group, request_end, &ctx, &detailed_check_sender);
+                    } else {
+                        if !matches!(group.state, GroupState::Owned { .. }) {
+                            catalog.remove(group.id);
+                            group.state = GroupState::Owned { since: request_end };
                         }
+                        scheduler.reschedule(group, request_end);
+                    }
+                }
+                // Batch responses leave out groups that no longer exist. One miss could be a
+                // glitch, so a group is only dropped once it misses two batches in a row.
+                for (_, entry) in current_batch.drain() {
+                    let mut group = entry.group;
+                    if group.state == GroupState::Missing {
+                        ctx.counters.deleted_groups.fetch_add(1, Ordering::Relaxed);
+                        catalog.remove(group.id);
+                        group.state = GroupState::Deleted;
                     } else {
-                        check_queue.0.send(TrackedGroup {
-                            id: group_info.id,
-                            processed_before: true,
-                        });
+                        group.state = GroupState::Missing;
                     }
+                    scheduler.reschedule(group, request_end);
                 }
                 #[allow(clippy::cast_possible_truncation)]
-                BATCH_CHECK_COUNTER.fetch_add(data.len() as u32, Ordering::Relaxed);
+                ctx.counters
+                    .batch_checks
+                    .fetch_add(data.len() as u32, Ordering::Relaxed);
             }
             Err(error) => {
-                for (id, tracked) in &current_batch {
-                    priority_check_queue.0.send(TrackedGroup {
-                        id: *id,
-                        processed_before: *tracked,
-                    });
-                }
-                if let Error::Api(error) = error {
-                    if error.message == RATE_LIMITED_MESSAGE {
-                        continue;
-                    }
+                // Keep the original due time so failed groups stay at the front of the schedule
+                scheduler.schedule_all(current_batch.drain().map(|(_, entry)| entry));
+                if error == ApiFailure::RateLimited {
+                    continue;
                 }
                 if retry_count >= settings.retry_limit {
                     break;
@@ -170,75 +375,986 @@ pub async fn batch_check(
         )
         .await;
     }
-    BATCH_PROXIES.fetch_sub(1, Ordering::Relaxed);
+    if ctx.counters.batch_proxies.fetch_sub(1, Ordering::Relaxed) == 1
+        && !ctx.shutdown.is_cancelled()
+    {
+        error!("All batch check tasks have stopped");
+        ctx.emit(FinderEvent::CheckTasksExhausted);
+    }
+}
+
+// Retries a step of claiming a joined group, so that one failed request doesn't leave the
+// account as a plain member of it
+async fn retry_step<T, F: Future<Output = ApiResult<T>>>(
+    ctx: &FinderContext,
+    step: &str,
+    id: Id,
+    mut request: impl FnMut() -> F,
+) -> ApiResult<T> {
+    let mut retry_wait = CLAIM_STEP_RETRY_WAIT;
+    let mut attempt = 0;
+    loop {
+        match request().await {
+            Ok(value) => return Ok(value),
+            // Retrying can't win a race that is already lost
+            Err(error)
+                if error != ApiFailure::AlreadyOwned
+                    && attempt < CLAIM_STEP_RETRY_LIMIT
+                    && !ctx.shutdown.is_cancelled() =>
+            {
+                attempt += 1;
+                warn!(
+                    "Failed to {} group {} (attempt {}), retrying in {:?}, error: {:?}",
+                    step, id, attempt, retry_wait, error
+                );
+                time::sleep(retry_wait).await;
+                retry_wait *= 2;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+// Returns whether the account left the group
+async fn leave_group(client: &impl ClaimClient, ctx: &FinderContext, id: Id, user_id: Id) -> bool {
+    match retry_step(ctx, "leave", id, || client.leave(id, user_id)).await {
+        Ok(()) => {
+            ctx.counters.groups_owned.fetch_sub(1, Ordering::Relaxed);
+            true
+        }
+        Err(error) => {
+            warn!("Failed to leave group {}, error: {:?}", id, error);
+            ctx.emit(FinderEvent::LeaveFailed { id });
+            false
+        }
+    }
+}
+
+async fn keep_or_leave(
+    client: &impl ClaimClient,
+    ctx: &FinderContext,
+    details: &DetailedGroupInfo,
+    funds: u64,
+    user_id: Id,
+    latencies: StageLatencies,
+) {
+    let (id, counters) = (details.id, &ctx.counters);
+    if ctx.policy.after_claim(details, funds) == PostClaimDecision::Leave {
+        if leave_group(client, ctx, id, user_id).await {
+            info!(
+                "Left group {} rejected by the claim policy ({} robux)",
+                id, funds
+            );
+            ctx.record(id, Some(funds), ClaimOutcome::Left);
+            ctx.emit(FinderEvent::Left { id, funds });
+        }
+    } else {
+        info!(
+            "Successfully claimed group {} ({} robux), claim sent {} after detection",
+            id, funds, latencies
+        );
+        ctx.record(id, Some(funds), ClaimOutcome::Kept);
+        ctx.emit(FinderEvent::Claimed { id, funds });
+        counters.robux_claimed.fetch_add(funds, Ordering::Relaxed);
+        counters.groups_claimed.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+async fn lose_claim(
+    client: &impl ClaimClient,
+    ctx: &FinderContext,
+    id: Id,
+    user_id: Id,
+    latencies: StageLatencies,
+) {
+    ctx.counters.claims_lost.fetch_add(1, Ordering::Relaxed);
+    let owner = match client.owner(id).await {
+        Ok(owner) => owner,
+        Err(error) => {
+            warn!(
+                "Failed to check the new owner of group {}, error: {:?}",
+                id, error
+            );
+            None
+        }
+    };
+    let winner = owner.map_or_else(
+        || "another account".to_owned(),
+        |owner| format!("user {owner}"),
+    );
+    info!(
+        "Lost group {} to {}, claim sent {} after detection",
+        id, winner, latencies
+    );
+    if leave_group(client, ctx, id, user_id).await {
+        ctx.emit(FinderEvent::LeftUnclaimed { id });
+    }
+    ctx.emit(FinderEvent::ClaimLost { id, owner });
+}
+
+// Claims a group the account just joined, leaving it again if that keeps failing
+async fn claim_joined(
+    client: &impl ClaimClient,
+    ctx: &FinderContext,
+    details: &DetailedGroupInfo,
+    user_id: Id,
+    times: &DetectionTimes,
+    dequeued: Instant,
+) -> Option<ClaimFailureReason> {
+    let id = details.id;
+    let latencies = StageLatencies::new(times, dequeued, Instant::now());
+    ctx.latency.record(latencies);
+    ctx.counters
+        .claims_attempted
+        .fetch_add(1, Ordering::Relaxed);
+    match retry_step(ctx, "claim", id, || client.claim(id)).await {
+        Ok(()) => {}
+        Err(ApiFailure::AlreadyOwned) => {
+            lose_claim(client, ctx, id, user_id, latencies).await;
+            return Some(ClaimFailureReason::Lost);
+        }
+        Err(error) => {
+            warn!(
+                "Failed to claim group {}, leaving it, error: {:?}",
+                id, error
+            );
+            if leave_group(client, ctx, id, user_id).await {
+                ctx.emit(FinderEvent::LeftUnclaimed { id });
+            }
+            return Some(ClaimFailureReason::Claim);
+        }
+    }
+    match retry_step(ctx, "get funds for", id, || client.funds(id)).await {
+        Ok(funds) => keep_or_leave(client, ctx, details, funds, user_id, latencies).await,
+        Err(error) => {
+            warn!(
+                "Failed to get funds for group {}, keeping it, claim sent {} after detection, error: {:?}",
+                id, latencies, error
+            );
+            ctx.record(id, None, ClaimOutcome::Kept);
+            ctx.emit(FinderEvent::FundsUnknown { id });
+            ctx.counters.groups_claimed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    None
+}
+
+fn at_group_limit(ctx: &FinderContext) -> bool {
+    let counters = &ctx.counters;
+    counters.groups_owned.load(Ordering::Relaxed) >= counters.group_limit.load(Ordering::Relaxed)
+}
+
+fn near_group_limit(ctx: &FinderContext) -> bool {
+    let counters = &ctx.counters;
+    counters
+        .groups_owned
+        .load(Ordering::Relaxed)
+        .saturating_add(PRUNE_MARGIN)
+        >= counters.group_limit.load(Ordering::Relaxed)
+}
+
+fn stop_at_group_limit(ctx: &FinderContext) {
+    // The capacity task and the claim stage can both see the limit
+    if ctx.shutdown.is_cancelled() {
+        return;
+    }
+    info!("Account is at the group limit, terminating");
+    ctx.emit(FinderEvent::GroupLimitReached);
+    ctx.shutdown.cancel();
+}
+
+// Returns whether a slot was freed
+async fn prune_lowest_value(client: &impl ClaimClient, ctx: &FinderContext, user_id: Id) -> bool {
+    let holdings = match portfolio::holdings(client, &*ctx.web, user_id).await {
+        Ok(holdings) => holdings,
+        Err(error) => {
+            warn!("Failed to list groups to prune, error: {:?}", error);
+            return false;
+        }
+    };
+    // Only groups the finder claimed itself, so groups the user created or was given are safe
+    let max_funds = ctx.settings.prune_max_funds;
+    let Some(lowest) = portfolio::lowest_value(&holdings, max_funds, |id| ctx.kept_by_finder(id))
+    else {
+        warn!(
+            "No group kept by the finder with at most {} robux to prune",
+            max_funds
+        );
+        return false;
+    };
+    let (id, funds) = (lowest.group.id, lowest.funds.unwrap_or_default());
+    if !leave_group(client, ctx, id, user_id).await {
+        return false;
+    }
+    info!("Pruned group {} ({} robux) to free a slot", id, funds);
+    ctx.record(id, Some(funds), ClaimOutcome::Pruned);
+    ctx.emit(FinderEvent::Pruned { id, funds });
+    true
+}
+
+// Groups joined or left outside the finder, and requests whose outcome is unknown, make the
+// local count drift from what the account actually has
+async fn reconcile_capacity(client: &impl ClaimClient, ctx: &FinderContext) {
+    let counters = &ctx.counters;
+    // Waits for a claim in progress, whose join the response would already count
+    let _turn = ctx.capacity_turn.lock().await;
+    let groups_owned = counters.groups_owned.load(Ordering::Relaxed);
+    let metadata = match client.metadata().await {
+        Ok(metadata) if metadata.group_limit > 0 => metadata,
+        Ok(_) => {
+            warn!("Group metadata reported no group limit, skipping reconciliation");
+            return;
+        }
+        Err(error) => {
+            warn!("Failed to refresh group metadata, error: {:?}", error);
+            return;
+        }
+    };
+    counters
+        .group_limit
+        .store(metadata.group_limit, Ordering::Relaxed);
+    if metadata.current_group_count != groups_owned {
+        counters
+            .groups_owned
+            .store(metadata.current_group_count, Ordering::Relaxed);
+        info!(
+            "Corrected owned group count from {} to {}",
+            groups_owned, metadata.current_group_count
+        );
+    }
+}
+
+// Keeps the owned group count in line with the account and, with pruning, frees slots before
+// claims need them. It never joins groups, so a claim in progress is always finished by the
+// claim stage.
+async fn manage_capacity(client: Arc<impl ClaimClient>, ctx: Arc<FinderContext>, user_id: Id) {
+    let mut next_reconcile = Instant::now() + CAPACITY_RECONCILE_INTERVAL;
+    loop {
+        if ctx.settings.auto_prune {
+            while near_group_limit(&ctx)
+                && !ctx.shutdown.is_cancelled()
+                && prune_lowest_value(&*client, &ctx, user_id).await
+            {}
+        }
+        if at_group_limit(&ctx) {
+            stop_at_group_limit(&ctx);
+            return;
+        }
+        ctx.slot_freed.notify_one();
+        tokio::select! {
+            () = time::sleep_until(next_reconcile) => {
+                next_reconcile = Instant::now() + CAPACITY_RECONCILE_INTERVAL;
+                reconcile_capacity(&*client, &ctx).await;
+            }
+            () = ctx.capacity_changed.notified() => {}
+        }
+    }
 }
 
 pub async fn claim(
-    client: impl AuthenticatedClient + Send,
-    claim_receiver: AsyncReceiver<Id>,
+    client: impl ClaimClient + 'static,
+    ctx: Arc<FinderContext>,
+    claim_queue: Arc<ClaimQueue>,
     metadata: Metadata,
     user_id: Id,
 ) {
-    let settings = SETTINGS.get().unwrap();
-    GROUPS_OWNED.store(metadata.current_group_count, Ordering::Relaxed);
+    ctx.counters
+        .groups_owned
+        .store(metadata.current_group_count, Ordering::Relaxed);
+    ctx.counters
+        .group_limit
+        .store(metadata.group_limit, Ordering::Relaxed);
+    let client = Arc::new(client);
+    let capacity = task::spawn(manage_capacity(client.clone(), ctx.clone(), user_id));
+    claim_groups(&*client, &ctx, &claim_queue, user_id).await;
+    capacity.abort();
+}
+
+async fn claim_groups(
+    client: &impl ClaimClient,
+    ctx: &FinderContext,
+    claim_queue: &ClaimQueue,
+    user_id: Id,
+) {
+    // Shutdown is only checked between candidates, so a claim is never abandoned partway
     loop {
-        let current_group = claim_receiver.recv().await.unwrap();
+        let ClaimCandidate {
+            group: mut tracked_group,
+            details,
+            times,
+        } = tokio::select! {
+            () = ctx.shutdown.cancelled() => return,
+            candidate = claim_queue.pop() => candidate,
+        };
+        let dequeued = Instant::now();
+        let turn = ctx.capacity_turn.lock().await;
+        // Joining at the limit would fail, so wait for the capacity task to prune a group,
+        // which stops the finder instead if nothing can be pruned
+        if at_group_limit(ctx) {
+            drop(turn);
+            claim_queue.requeue(ClaimCandidate {
+                group: tracked_group,
+                details,
+                times,
+            });
+            if !ctx.settings.auto_prune {
+                stop_at_group_limit(ctx);
+                return;
+            }
+            ctx.capacity_changed.notify_one();
+            tokio::select! {
+                () = ctx.shutdown.cancelled() => return,
+                () = ctx.slot_freed.notified() => continue,
+            }
+        }
+        let current_group = tracked_group.id;
         info!("Claiming group {}", current_group);
-        match client.join_group(current_group, None).await {
-            Ok(_) => match client.claim_group(current_group).await {
-                Ok(_) => match client.get_group_funds(current_group).await {
-                    Ok(funds) => {
-                        if funds < settings.funds_threshold {
-                            if client
-                                .remove_user_from_group(current_group, user_id)
-                                .await
-                                .is_ok()
-                            {
-                                info!(
-                                    "Left group {} with insufficient funds ({} robux)",
-                                    current_group, funds
-                                );
-                            } else {
-                                warn!(
-                                    "Failed to leave group {} with insufficient funds ({} robux)",
-                                    current_group, funds
-                                );
-                            }
-                        } else {
-                            info!(
-                                "Successfully claimed group {} ({} robux)",
-                                current_group, funds
-                            );
-                            #[allow(clippy::cast_possible_truncation)]
-                            ROBUX_CLAIMED.fetch_add(funds as u32, Ordering::Relaxed);
-                            GROUPS_CLAIMED.fetch_add(1, Ordering::Relaxed);
-                            let current_group_count =
-                                GROUPS_OWNED.fetch_add(1, Ordering::Relaxed) + 1;
-                            if current_group_count >= metadata.group_limit {
-                                info!("Account is at the group limit, terminating");
-                                process::exit(0);
-                            }
-                        }
-                    }
-                    Err(error) => warn!(
-                        "Failed to get funds for group {}, error: {:?}",
-                        current_group, error
-                    ),
-                },
-                Err(error) => warn!(
-                    "Failed to claim group {}, error: {:?}",
-                    current_group, error
-                ),
-            },
+        let failure_reason = match client.join(current_group).await {
+            Ok(()) => {
+                ctx.counters.groups_owned.fetch_add(1, Ordering::Relaxed);
+                claim_joined(client, ctx, &details, user_id, &times, dequeued).await
+            }
             Err(error) => {
                 warn!("Failed to join group {}, error: {:?}", current_group, error);
-                if let Error::Api(error) = error {
-                    if error.message == CAPTCHA_MESSAGE {
-                        error!("Browser ID is invalid");
-                        process::exit(0);
-                    }
+                if error == ApiFailure::ChallengeRequired {
+                    error!("Browser ID is invalid");
+                    ctx.emit(FinderEvent::Fatal {
+                        message: "Browser ID is invalid".to_owned(),
+                    });
+                    ctx.shutdown.cancel();
+                    return;
                 }
+                Some(ClaimFailureReason::Join)
+            }
+        };
+        // Lost races are reported by `lose_claim`
+        if let Some(reason) = failure_reason.filter(|reason| *reason != ClaimFailureReason::Lost) {
+            ctx.emit(FinderEvent::ClaimFailed {
+                id: current_group,
+                reason,
+            });
+        }
+        tracked_group.state = match (failure_reason, tracked_group.state) {
+            (None, _) => GroupState::Claimed,
+            (Some(ClaimFailureReason::Lost), _) => GroupState::Owned {
+                since: Instant::now(),
+            },
+            (Some(reason), GroupState::ClaimFailed { attempts, .. }) => GroupState::ClaimFailed {
+                reason,
+                attempts: attempts + 1,
+            },
+            (Some(reason), _) => GroupState::ClaimFailed {
+                reason,
+                attempts: 1,
+            },
+        };
+        drop(turn);
+        ctx.scheduler.reschedule(tracked_group, Instant::now());
+        claim_queue.finish(current_group);
+        if ctx.settings.auto_prune {
+            ctx.capacity_changed.notify_one();
+        } else if at_group_limit(ctx) {
+            stop_at_group_limit(ctx);
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        sync::{atomic::Ordering, Arc},
+        time::{Duration, SystemTime},
+    };
+
+    use roblox_api::apis::groups::Metadata;
+    use tokio::{
+        task,
+        time::{self, Instant},
+    };
+
+    use super::{
+        batch_check, claim, detailed_check, ClaimCandidate, ClaimFailureReason, Detection,
+        GroupState, TrackedGroup,
+    };
+    use crate::{
+        claim_queue::ClaimQueue,
+        client::{ApiFailure, CheckClient, DetailedGroupInfo, WebClient},
+        config::Settings,
+        constants::{CAPACITY_RECONCILE_INTERVAL, CLAIM_STEP_RETRY_LIMIT},
+        context::FinderContext,
+        events::FinderEvent,
+        fake::{id, settings, FakeCall, FakeGroup, FakeRoblox},
+        hook::ClaimOutcome,
+        latency::DetectionTimes,
+        ledger::{Ledger, LedgerEntry},
+    };
+
+    fn context(
+        fake: &FakeRoblox,
+        groups: impl IntoIterator<Item = TrackedGroup>,
+    ) -> Arc<FinderContext> {
+        let mut ctx = FinderContext::new(settings());
+        ctx.web = Box::new(fake.clone());
+        let ctx = Arc::new(ctx);
+        let now = Instant::now();
+        for group in groups {
+            ctx.scheduler.schedule(group, now);
+        }
+        ctx
+    }
+
+    async fn claim_queue(
+        fake: &FakeRoblox,
+        group_ids: impl IntoIterator<Item = u64>,
+    ) -> Arc<ClaimQueue> {
+        let queue = Arc::new(ClaimQueue::new(10));
+        for group_id in group_ids {
+            queue.push(ClaimCandidate {
+                group: TrackedGroup {
+                    id: id(group_id),
+                    ..TrackedGroup::default()
+                },
+                details: DetailedGroupInfo {
+                    created: fake.created(id(group_id)).await.unwrap(),
+                    ..fake.detailed_info(id(group_id)).await.unwrap()
+                },
+                times: detection_times(),
+            });
+        }
+        queue
+    }
+
+    fn detection_times() -> DetectionTimes {
+        DetectionTimes {
+            observed: Instant::now(),
+            detailed: Instant::now(),
+        }
+    }
+
+    async fn wait_until(mut condition: impl FnMut() -> bool) {
+        time::timeout(Duration::from_secs(5), async {
+            while !condition() {
+                time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("condition was not reached in time");
+    }
+
+    #[tokio::test]
+    async fn batch_check_sends_abandoned_groups_to_detailed_check() {
+        let fake = FakeRoblox::new(id(1), 100);
+        fake.insert_group(
+            id(1),
+            FakeGroup {
+                owner: Some(id(2)),
+                ..FakeGroup::default()
+            },
+        );
+        fake.insert_group(id(2), FakeGroup::default());
+        let ctx = context(
+            &fake,
+            [
+                TrackedGroup {
+                    id: id(1),
+                    state: GroupState::Unseen,
+                },
+                TrackedGroup {
+                    id: id(2),
+                    state: GroupState::Owned {
+                        since: Instant::now(),
+                    },
+                },
+                TrackedGroup {
+                    id: id(3),
+                    state: GroupState::Unseen,
+                },
+            ],
+        );
+        let (detailed_sender, detailed_receiver) = kanal::unbounded();
+        let worker = task::spawn(batch_check(fake.clone(), ctx.clone(), detailed_sender));
+
+        let Detection { group, observed } =
+            time::timeout(Duration::from_secs(5), detailed_receiver.as_async().recv())
+                .await
+                .unwrap()
+                .unwrap();
+        assert!(observed <= Instant::now());
+        assert_eq!(group.id, id(2));
+        assert!(matches!(group.state, GroupState::Ownerless { .. }));
+        // The missing group is only dropped once it misses a second batch
+        assert_eq!(ctx.counters.deleted_groups.load(Ordering::Relaxed), 0);
+        wait_until(|| ctx.counters.deleted_groups.load(Ordering::Relaxed) == 1).await;
+        worker.abort();
+        assert_eq!(fake.calls(FakeCall::BatchInfo), 2);
+        assert!(detailed_receiver.is_empty());
+        assert_eq!(ctx.catalog.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn detailed_check_forwards_public_groups_to_claim() {
+        let fake = FakeRoblox::new(id(1), 100);
+        fake.insert_group(
+            id(1),
+            FakeGroup {
+                public_entry_allowed: true,
+                created: Some(SystemTime::UNIX_EPOCH),
+                ..FakeGroup::default()
+            },
+        );
+        fake.insert_group(id(2), FakeGroup::default());
+        fake.insert_group(
+            id(3),
+            FakeGroup {
+                public_entry_allowed: true,
+                is_locked: true,
+                ..FakeGroup::default()
+            },
+        );
+        fake.fail_next(FakeCall::DetailedInfo, ApiFailure::RateLimited);
+        let ctx = context(&fake, []);
+        let (check_sender, check_receiver) = kanal::unbounded();
+        let claim_queue = Arc::new(ClaimQueue::new(10));
+        for group_id in 1..=4 {
+            check_sender
+                .send(Detection {
+                    group: TrackedGroup {
+                        id: id(group_id),
+                        state: GroupState::Ownerless {
+                            since: Instant::now(),
+                        },
+                    },
+                    observed: Instant::now(),
+                })
+                .unwrap();
+        }
+        let worker = task::spawn(detailed_check(
+            fake.clone(),
+            ctx.clone(),
+            check_receiver,
+            kanal::unbounded(),
+            claim_queue.clone(),
+        ));
+
+        wait_until(|| fake.calls(FakeCall::DetailedInfo) == 5).await;
+        wait_until(|| ctx.scheduler.len() == 1).await;
+        worker.abort();
+        assert_eq!(ctx.counters.deleted_groups.load(Ordering::Relaxed), 1);
+        let candidate = claim_queue.try_pop().unwrap();
+        assert_eq!(candidate.group.id, id(1));
+        assert_eq!(candidate.details.id, id(1));
+        // The detailed info endpoint doesn't report it, so it's fetched separately
+        assert_eq!(candidate.details.created, Some(SystemTime::UNIX_EPOCH));
+        assert!(candidate.times.observed <= candidate.times.detailed);
+        assert!(claim_queue.is_empty());
+    }
+
+    #[tokio::test]
+    async fn claim_keeps_funded_groups_and_leaves_the_rest() {
+        let fake = FakeRoblox::new(id(1), 100);
+        for (group_id, funds) in [(1, 100), (2, 0), (3, 100)] {
+            fake.insert_group(
+                id(group_id),
+                FakeGroup {
+                    funds,
+                    public_entry_allowed: true,
+                    ..FakeGroup::default()
+                },
+            );
+        }
+        // The oldest group is claimed first, and its join fails
+        fake.update_group(id(3), |group| group.created = Some(SystemTime::UNIX_EPOCH));
+        fake.fail_next(FakeCall::Join, ApiFailure::RateLimited);
+        let ctx = context(&fake, []);
+        let worker = task::spawn(claim(
+            fake.clone(),
+            ctx.clone(),
+            claim_queue(&fake, [1, 2, 3]).await,
+            Metadata {
+                group_limit: 100,
+                current_group_count: 0,
+            },
+            fake.user_id,
+        ));
+
+        wait_until(|| fake.calls(FakeCall::Join) == 3 && ctx.scheduler.len() == 1).await;
+        worker.abort();
+        assert_eq!(fake.group(id(1)).unwrap().owner, Some(fake.user_id));
+        let left = fake.group(id(2)).unwrap();
+        assert_eq!(left.owner, None);
+        assert!(!left.members.contains(&fake.user_id));
+        // The failed join is scheduled for a retry instead of being claimed
+        assert_eq!(fake.group(id(3)).unwrap().owner, None);
+        assert_eq!(fake.groups_owned(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn claim_retries_failed_steps_and_leaves_unclaimable_groups() {
+        let fake = FakeRoblox::new(id(1), 100);
+        for group_id in [1, 2] {
+            fake.insert_group(
+                id(group_id),
+                FakeGroup {
+                    funds: 100,
+                    public_entry_allowed: true,
+                    ..FakeGroup::default()
+                },
+            );
+        }
+        // Group 1 fails every claim attempt, group 2 fails its first claim and funds request
+        for _ in 0..=CLAIM_STEP_RETRY_LIMIT + 1 {
+            fake.fail_next(FakeCall::Claim, ApiFailure::Other("timed out".to_owned()));
+        }
+        fake.fail_next(FakeCall::Funds, ApiFailure::Other("timed out".to_owned()));
+        let ctx = context(&fake, []);
+        let events = ctx.events();
+        let worker = task::spawn(claim(
+            fake.clone(),
+            ctx.clone(),
+            claim_queue(&fake, [1, 2]).await,
+            Metadata {
+                group_limit: 100,
+                current_group_count: 0,
+            },
+            fake.user_id,
+        ));
+
+        // Long enough for every retry's backoff
+        time::sleep(Duration::from_mins(1)).await;
+        wait_until(|| ctx.counters.groups_claimed.load(Ordering::Relaxed) == 1).await;
+        worker.abort();
+        let unclaimable = fake.group(id(1)).unwrap();
+        assert_eq!(unclaimable.owner, None);
+        assert!(!unclaimable.members.contains(&fake.user_id));
+        assert_eq!(fake.group(id(2)).unwrap().owner, Some(fake.user_id));
+        assert_eq!(fake.groups_joined(), 1);
+        assert_eq!(ctx.counters.groups_owned.load(Ordering::Relaxed), 1);
+        let mut received = Vec::new();
+        while let Ok(Some(event)) = events.try_recv() {
+            received.push(event);
+        }
+        assert_eq!(
+            received,
+            [
+                FinderEvent::LeftUnclaimed { id: id(1) },
+                FinderEvent::ClaimFailed {
+                    id: id(1),
+                    reason: ClaimFailureReason::Claim
+                },
+                FinderEvent::Claimed {
+                    id: id(2),
+                    funds: 100
+                },
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn claim_reports_races_lost_to_another_account() {
+        let fake = FakeRoblox::new(id(1), 100);
+        // Claimed by someone else after the detailed check
+        fake.insert_group(
+            id(1),
+            FakeGroup {
+                owner: Some(id(7)),
+                public_entry_allowed: true,
+                ..FakeGroup::default()
+            },
+        );
+        let ctx = context(&fake, []);
+        let events = ctx.events();
+        let worker = task::spawn(claim(
+            fake.clone(),
+            ctx.clone(),
+            claim_queue(&fake, [1]).await,
+            Metadata {
+                group_limit: 100,
+                current_group_count: 0,
+            },
+            fake.user_id,
+        ));
+
+        wait_until(|| ctx.scheduler.len() == 1).await;
+        worker.abort();
+        // Lost races aren't retried
+        assert_eq!(fake.calls(FakeCall::Claim), 1);
+        assert_eq!(fake.groups_joined(), 0);
+        assert_eq!(ctx.counters.claims_lost.load(Ordering::Relaxed), 1);
+        assert_eq!(ctx.counters.lost_race_rate(), Some(1.0));
+        let mut received = Vec::new();
+        while let Ok(Some(event)) = events.try_recv() {
+            received.push(event);
+        }
+        assert_eq!(
+            received,
+            [
+                FinderEvent::LeftUnclaimed { id: id(1) },
+                FinderEvent::ClaimLost {
+                    id: id(1),
+                    owner: Some(id(7))
+                },
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn claim_stops_when_groups_joined_elsewhere_fill_the_account() {
+        let fake = FakeRoblox::new(id(1), 3);
+        for group_id in 1..=3 {
+            fake.insert_group(
+                id(group_id),
+                FakeGroup {
+                    members: std::iter::once(fake.user_id).collect(),
+                    ..FakeGroup::default()
+                },
+            );
+        }
+        fake.insert_group(
+            id(4),
+            FakeGroup {
+                public_entry_allowed: true,
+                ..FakeGroup::default()
+            },
+        );
+        let ctx = context(&fake, []);
+        let events = ctx.events();
+        let claim_queue = claim_queue(&fake, []).await;
+        let worker = task::spawn(claim(
+            fake.clone(),
+            ctx.clone(),
+            claim_queue.clone(),
+            Metadata {
+                group_limit: 3,
+                current_group_count: 1,
+            },
+            fake.user_id,
+        ));
+
+        time::sleep(CAPACITY_RECONCILE_INTERVAL * 2).await;
+        assert!(worker.is_finished());
+        assert!(ctx.shutdown.is_cancelled());
+        assert_eq!(ctx.counters.groups_owned.load(Ordering::Relaxed), 3);
+        assert_eq!(
+            events.try_recv().unwrap(),
+            Some(FinderEvent::GroupLimitReached)
+        );
+        // Candidates queued after the reconciliation are never joined
+        claim_queue.push(ClaimCandidate {
+            group: TrackedGroup {
+                id: id(4),
+                ..TrackedGroup::default()
+            },
+            details: fake.detailed_info(id(4)).await.unwrap(),
+            times: detection_times(),
+        });
+        assert_eq!(fake.calls(FakeCall::Join), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reconciliation_waits_for_the_claim_in_progress() {
+        let fake = FakeRoblox::new(id(1), 3);
+        for group_id in 1..=2 {
+            fake.insert_group(
+                id(group_id),
+                FakeGroup {
+                    members: std::iter::once(fake.user_id).collect(),
+                    ..FakeGroup::default()
+                },
+            );
+        }
+        fake.insert_group(
+            id(3),
+            FakeGroup {
+                public_entry_allowed: true,
+                ..FakeGroup::default()
+            },
+        );
+        for _ in 0..=CLAIM_STEP_RETRY_LIMIT {
+            fake.fail_next(FakeCall::Funds, ApiFailure::Other("timed out".to_owned()));
+        }
+        let ctx = context(&fake, []);
+        let events = ctx.events();
+        let claim_queue = claim_queue(&fake, []).await;
+        let worker = task::spawn(claim(
+            fake.clone(),
+            ctx.clone(),
+            claim_queue.clone(),
+            Metadata {
+                group_limit: 3,
+                current_group_count: 1,
+            },
+            fake.user_id,
+        ));
+
+        // The funds retries are still backing off when reconciliation is due
+        time::sleep(CAPACITY_RECONCILE_INTERVAL.saturating_sub(Duration::from_secs(5))).await;
+        claim_queue.push(ClaimCandidate {
+            group: TrackedGroup {
+                id: id(3),
+                ..TrackedGroup::default()
+            },
+            details: fake.detailed_info(id(3)).await.unwrap(),
+            times: detection_times(),
+        });
+        time::timeout(Duration::from_secs(30), worker)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(ctx.counters.groups_claimed.load(Ordering::Relaxed), 1);
+        assert_eq!(fake.group(id(3)).unwrap().owner, Some(fake.user_id));
+        let mut received = Vec::new();
+        while let Ok(Some(event)) = events.try_recv() {
+            received.push(event);
+        }
+        // Reconciliation only sees the account is full once the claim is done
+        assert_eq!(
+            received,
+            [
+                FinderEvent::FundsUnknown { id: id(3) },
+                FinderEvent::GroupLimitReached,
+            ]
+        );
+    }
+
+    // A context that prunes groups with up to 30 robux, whose ledger records `kept` as claimed
+    // and kept by the finder
+    fn pruning_context(
+        fake: &FakeRoblox,
+        name: &str,
+        kept: impl IntoIterator<Item = u64>,
+    ) -> Arc<FinderContext> {
+        let path = env::temp_dir().join(format!("{name}_{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let ledger = Ledger::open(&path).unwrap();
+        // Entries are still tracked in memory once the file is gone
+        let _ = fs::remove_file(&path);
+        for group_id in kept {
+            ledger
+                .record(&LedgerEntry::now(id(group_id), None, ClaimOutcome::Kept))
+                .unwrap();
+        }
+        let mut ctx = FinderContext::new(Settings {
+            auto_prune: true,
+            prune_max_funds: 30,
+            ..settings()
+        });
+        ctx.ledger = Some(ledger);
+        ctx.web = Box::new(fake.clone());
+        Arc::new(ctx)
+    }
+
+    // Gives the account owned groups with the given funds, and adds public group 100 to claim
+    fn fill_account(fake: &FakeRoblox, funds: impl IntoIterator<Item = (u64, u64)>) {
+        for (group_id, funds) in funds {
+            fake.insert_group(
+                id(group_id),
+                FakeGroup {
+                    owner: Some(fake.user_id),
+                    funds,
+                    members: std::iter::once(fake.user_id).collect(),
+                    ..FakeGroup::default()
+                },
+            );
+        }
+        fake.insert_group(
+            id(100),
+            FakeGroup {
+                funds: 100,
+                public_entry_allowed: true,
+                ..FakeGroup::default()
+            },
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn capacity_task_prunes_the_lowest_value_groups_near_the_limit() {
+        let fake = FakeRoblox::new(id(1), 3);
+        fill_account(&fake, [(1, 25), (2, 20)]);
+        let ctx = pruning_context(&fake, "prune_ledger", [1, 2]);
+        let events = ctx.events();
+        let worker = task::spawn(claim(
+            fake.clone(),
+            ctx.clone(),
+            claim_queue(&fake, [100]).await,
+            Metadata {
+                group_limit: 3,
+                current_group_count: 2,
+            },
+            fake.user_id,
+        ));
+
+        // One slot is free at the start and again after the claim, so each time the cheapest
+        // group is pruned
+        wait_until(|| {
+            fake.group(id(100)).unwrap().owner == Some(fake.user_id) && fake.groups_joined() == 1
+        })
+        .await;
+        time::sleep(Duration::from_secs(1)).await;
+        assert!(!worker.is_finished());
+        worker.abort();
+        assert!(ctx.kept_by_finder(id(100)));
+        assert!(!ctx.kept_by_finder(id(1)) && !ctx.kept_by_finder(id(2)));
+        let mut pruned = Vec::new();
+        while let Ok(Some(event)) = events.try_recv() {
+            if matches!(event, FinderEvent::Pruned { .. }) {
+                pruned.push(event);
             }
         }
+        assert_eq!(
+            pruned,
+            [
+                FinderEvent::Pruned {
+                    id: id(2),
+                    funds: 20
+                },
+                FinderEvent::Pruned {
+                    id: id(1),
+                    funds: 25
+                },
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn capacity_task_only_prunes_cheap_groups_the_finder_kept() {
+        let fake = FakeRoblox::new(id(1), 3);
+        // Group 1 is over the funds cap, and group 2 wasn't claimed by the finder
+        fill_account(&fake, [(1, 50), (2, 0)]);
+        fake.insert_group(
+            id(3),
+            FakeGroup {
+                members: std::iter::once(fake.user_id).collect(),
+                ..FakeGroup::default()
+            },
+        );
+        let ctx = pruning_context(&fake, "prune_refused_ledger", [1, 3]);
+        let events = ctx.events();
+        let claim_queue = claim_queue(&fake, [100]).await;
+        let worker = task::spawn(claim(
+            fake.clone(),
+            ctx.clone(),
+            claim_queue.clone(),
+            Metadata {
+                group_limit: 3,
+                current_group_count: 3,
+            },
+            fake.user_id,
+        ));
+
+        time::timeout(Duration::from_secs(5), worker)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(fake.groups_owned(), 2);
+        assert_eq!(fake.groups_joined(), 3);
+        assert_eq!(fake.calls(FakeCall::Join), 0);
+        // The candidate goes back in the queue rather than being dropped
+        assert_eq!(claim_queue.try_pop().unwrap().group.id, id(100));
+        assert_eq!(
+            events.try_recv().unwrap(),
+            Some(FinderEvent::GroupLimitReached)
+        );
     }
 }
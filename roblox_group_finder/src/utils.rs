@@ -1,7 +1,13 @@
-use std::num::NonZeroUsize;
+use std::{
+    num::NonZeroUsize,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use async_trait::async_trait;
+use reqwest::{header::COOKIE, StatusCode};
 use roblox_api::apis::{self, groups::GroupsApi, Id, RequestResult};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinSet;
 
 use crate::constants;
 
@@ -49,41 +55,675 @@ fn get_partitioning_ids(
     partitioning_ids
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatestIdSearch {
+    pub id: Id,
+    pub requests: usize,
+}
+
+// A kind of object with monotonically increasing ids that can be looked up in batches
+#[async_trait]
+pub trait LatestIdProbe {
+    type Error: Send;
+
+    fn max_batch_size(&self) -> NonZeroUsize;
+
+    async fn get_present_ids(&self, ids: &[Id]) -> Result<Vec<Id>, Self::Error>;
+}
+
+pub struct GroupIdProbe<'a, T: ?Sized>(pub &'a T);
+
+#[async_trait]
+impl<T: GroupsApi + Sync + ?Sized> LatestIdProbe for GroupIdProbe<'_, T> {
+    type Error = apis::Error;
+
+    fn max_batch_size(&self) -> NonZeroUsize {
+        NonZeroUsize::new(constants::MAX_IDS_IN_BATCH_REQUEST).unwrap()
+    }
+
+    async fn get_present_ids(&self, ids: &[Id]) -> RequestResult<Vec<Id>> {
+        Ok(self
+            .0
+            .get_batch_info(ids.iter().copied())
+            .await?
+            .iter()
+            .map(|group_info| group_info.id)
+            .collect())
+    }
+}
+
+// roblox_api has no batch lookup for users, but the endpoint doesn't need authentication
+pub struct UserIdProbe<'a>(pub &'a reqwest::Client);
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UsersRequest<'a> {
+    user_ids: &'a [Id],
+    exclude_banned_users: bool,
+}
+
+#[derive(Deserialize)]
+struct UsersResponse {
+    data: Vec<UserEntry>,
+}
+
+#[derive(Deserialize)]
+struct UserEntry {
+    id: Id,
+}
+
+#[async_trait]
+impl LatestIdProbe for UserIdProbe<'_> {
+    type Error = reqwest::Error;
+
+    fn max_batch_size(&self) -> NonZeroUsize {
+        NonZeroUsize::new(constants::MAX_IDS_IN_USERS_BATCH_REQUEST).unwrap()
+    }
+
+    async fn get_present_ids(&self, ids: &[Id]) -> Result<Vec<Id>, Self::Error> {
+        let response: UsersResponse = self
+            .0
+            .post(constants::USERS_BATCH_API_URL)
+            .json(&UsersRequest {
+                user_ids: ids,
+                // Banned users still take up their ids
+                exclude_banned_users: false,
+            })
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(response.data.into_iter().map(|user| user.id).collect())
+    }
+}
+
+// Place details need authentication, but unlike the asset endpoints they only report places
+pub struct PlaceIdProbe<'a> {
+    pub http: &'a reqwest::Client,
+    pub cookie: &'a str,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlaceEntry {
+    place_id: Id,
+}
+
+#[async_trait]
+impl LatestIdProbe for PlaceIdProbe<'_> {
+    type Error = reqwest::Error;
+
+    fn max_batch_size(&self) -> NonZeroUsize {
+        NonZeroUsize::new(constants::MAX_IDS_IN_PLACES_BATCH_REQUEST).unwrap()
+    }
+
+    async fn get_present_ids(&self, ids: &[Id]) -> Result<Vec<Id>, Self::Error> {
+        let places: Vec<PlaceEntry> = self
+            .http
+            .get(constants::PLACES_BATCH_API_URL)
+            .query(&ids.iter().map(|id| ("placeIds", id)).collect::<Vec<_>>())
+            .header(COOKIE, format!(".ROBLOSECURITY={}", self.cookie))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(places.into_iter().map(|place| place.place_id).collect())
+    }
+}
+
+// Assets and badges have no public batch lookup, so each id is its own request
+async fn get_present_ids_one_by_one(
+    http: &reqwest::Client,
+    ids: &[Id],
+    url: impl Fn(Id) -> String,
+) -> reqwest::Result<Vec<Id>> {
+    let mut lookups = JoinSet::new();
+    for &id in ids {
+        let request = http.get(url(id)).send();
+        lookups.spawn(async move { (id, request.await) });
+    }
+    let mut present = Vec::new();
+    while let Some(lookup) = lookups.join_next().await {
+        let (id, response) = lookup.expect("Id lookup task panicked");
+        let response = response?;
+        // Missing ids are reported as not found or as a bad request, depending on the endpoint
+        if !matches!(
+            response.status(),
+            StatusCode::NOT_FOUND | StatusCode::BAD_REQUEST
+        ) {
+            response.error_for_status()?;
+            present.push(id);
+        }
+    }
+    Ok(present)
+}
+
+pub struct AssetIdProbe<'a>(pub &'a reqwest::Client);
+
+#[async_trait]
+impl LatestIdProbe for AssetIdProbe<'_> {
+    type Error = reqwest::Error;
+
+    fn max_batch_size(&self) -> NonZeroUsize {
+        NonZeroUsize::new(constants::MAX_CONCURRENT_ID_LOOKUPS).unwrap()
+    }
+
+    async fn get_present_ids(&self, ids: &[Id]) -> Result<Vec<Id>, Self::Error> {
+        get_present_ids_one_by_one(self.0, ids, |id| {
+            format!("{}/{id}/details", constants::ASSET_DETAILS_API_URL)
+        })
+        .await
+    }
+}
+
+pub struct BadgeIdProbe<'a>(pub &'a reqwest::Client);
+
+#[async_trait]
+impl LatestIdProbe for BadgeIdProbe<'_> {
+    type Error = reqwest::Error;
+
+    fn max_batch_size(&self) -> NonZeroUsize {
+        NonZeroUsize::new(constants::MAX_CONCURRENT_ID_LOOKUPS).unwrap()
+    }
+
+    async fn get_present_ids(&self, ids: &[Id]) -> Result<Vec<Id>, Self::Error> {
+        get_present_ids_one_by_one(self.0, ids, |id| {
+            format!("{}/{id}", constants::BADGE_DETAILS_API_URL)
+        })
+        .await
+    }
+}
+
+// Consecutive ids starting at `start`, so that a run of deleted ids shorter than the window
+// can't hide the groups above it
+fn id_window(start: u64, len: usize) -> Vec<Id> {
+    if len == 0 {
+        return Vec::new();
+    }
+    let end = start.saturating_add(len as u64 - 1).min(Id::MAX.get());
+    (start..=end).filter_map(Id::new).collect()
+}
+
+async fn get_max_present_id<P: LatestIdProbe + Sync + ?Sized>(
+    api: &P,
+    ids: &[Id],
+    requests: &mut usize,
+) -> Result<Option<Id>, P::Error> {
+    let mut max_present_id = None;
+    for chunk in ids.chunks(api.max_batch_size().get()) {
+        *requests += 1;
+        max_present_id = max_present_id.max(api.get_present_ids(chunk).await?.into_iter().max());
+    }
+    Ok(max_present_id)
+}
+
+async fn bisect_latest_id<P: LatestIdProbe + Sync + ?Sized>(
+    api: &P,
+    mut low_id: Id,
+    mut high_id: Id,
+    requests: &mut usize,
+) -> Result<Id, P::Error> {
+    while high_id.get() - low_id.get() > 1 {
+        let ids_to_check = get_partitioning_ids(low_id, high_id, api.max_batch_size());
+        *requests += 1;
+        match api.get_present_ids(&ids_to_check).await {
+            Ok(results) => {
+                let max_present_id = results.into_iter().max();
+                high_id = max_present_id.map_or_else(
+                    || ids_to_check[0],
+                    |max_present_id| {
+                        low_id = max_present_id;
+                        *ids_to_check
+                            .get(
+                                ids_to_check
+                                    .iter()
+                                    .position(|&id| id == max_present_id)
+                                    .unwrap()
+                                    + 1,
+                            )
+                            .unwrap_or(&high_id)
+                    },
+                );
+            }
+            Err(error) => {
+                return Err(error);
+            }
+        }
+    }
+    Ok(low_id)
+}
+
+// Finds the first window of `window` ids above `low_id` with nothing in it, moving `low_id` up to
+// the highest id seen on the way
+async fn gallop_up<P: LatestIdProbe + Sync + ?Sized>(
+    api: &P,
+    low_id: &mut Id,
+    window: usize,
+    requests: &mut usize,
+) -> Result<Id, P::Error> {
+    let batch_size = api.max_batch_size().get();
+    let mut step = batch_size as u64;
+    loop {
+        let start = low_id.get().saturating_add(step).min(Id::MAX.get());
+        // Only look at the rest of the window once the first batch of it is empty
+        let mut max_present_id =
+            get_max_present_id(api, &id_window(start, batch_size.min(window)), requests).await?;
+        if max_present_id.is_none() && window > batch_size {
+            max_present_id = get_max_present_id(
+                api,
+                &id_window(start.saturating_add(batch_size as u64), window - batch_size),
+                requests,
+            )
+            .await?;
+        }
+        match max_present_id {
+            Some(id) if id > *low_id => {
+                *low_id = id;
+                step = step.saturating_mul(2);
+            }
+            _ => return Ok(Id::new(start).unwrap()),
+        }
+    }
+}
+
+// Bisects between `low_id` and `high_id`, then only accepts the result once nothing is found
+// within `max_gap` ids above it
+async fn search_latest_id<P: LatestIdProbe + Sync + ?Sized>(
+    api: &P,
+    mut low_id: Id,
+    mut high_id: Id,
+    max_gap: usize,
+    requests: &mut usize,
+) -> Result<Id, P::Error> {
+    loop {
+        low_id = bisect_latest_id(api, low_id, high_id, requests).await?;
+        match get_max_present_id(api, &id_window(low_id.get() + 1, max_gap), requests).await? {
+            Some(id) if id > low_id => {
+                low_id = id;
+                high_id = gallop_up(api, &mut low_id, max_gap, requests).await?;
+            }
+            _ => return Ok(low_id),
+        }
+    }
+}
+
+pub async fn find_latest_id_from<P: LatestIdProbe + Sync + ?Sized>(
+    api: &P,
+    hint: Id,
+    max_gap: usize,
+) -> Result<LatestIdSearch, P::Error> {
+    let mut requests = 0;
+
+    // Gallop downwards until a window containing an id is found, in case the hint is
+    // past the frontier
+    let (mut start, mut step) = (hint.get(), api.max_batch_size().get() as u64);
+    let mut empty_window_start = None;
+    let mut low_id = loop {
+        if let Some(id) = get_max_present_id(
+            api,
+            &id_window(start, api.max_batch_size().get()),
+            &mut requests,
+        )
+        .await?
+        {
+            break id;
+        }
+        if start == Id::MIN.get() {
+            return Ok(LatestIdSearch {
+                id: Id::MIN,
+                requests,
+            });
+        }
+        empty_window_start = Some(start);
+        start = start.saturating_sub(step).max(Id::MIN.get());
+        step = step.saturating_mul(2);
+    };
+
+    // An empty window may just be a run of deleted ids, which the confirmation in
+    // `search_latest_id` recovers from
+    let high_id = match empty_window_start {
+        Some(start) if start > low_id.get() => Id::new(start).unwrap(),
+        _ => gallop_up(api, &mut low_id, max_gap, &mut requests).await?,
+    };
+    let id = search_latest_id(api, low_id, high_id, max_gap, &mut requests).await?;
+    Ok(LatestIdSearch { id, requests })
+}
+
+pub async fn find_latest_id<P: LatestIdProbe + Sync + ?Sized>(
+    api: &P,
+    max_gap: usize,
+) -> Result<LatestIdSearch, P::Error> {
+    let mut requests = 0;
+    let id = search_latest_id(api, Id::MIN, Id::MAX, max_gap, &mut requests).await?;
+    Ok(LatestIdSearch { id, requests })
+}
+
 #[async_trait]
 pub trait GroupsApiExt: apis::groups::GroupsApi {
-    async fn get_latest_group_id(&self) -> RequestResult<Id> {
-        let (mut low_id, mut high_id) = (Id::MIN, Id::MAX);
-        while high_id.get() - low_id.get() > 1 {
-            let ids_to_check = get_partitioning_ids(
-                low_id,
-                high_id,
-                NonZeroUsize::new(constants::MAX_IDS_IN_BATCH_REQUEST).unwrap(),
-            );
-            match self.get_batch_info(ids_to_check.iter().copied()).await {
-                Ok(results) => {
-                    let max_present_id = results.last().map(|group_info| group_info.id);
-                    high_id = max_present_id.map_or_else(
-                        || ids_to_check[0],
-                        |max_present_id| {
-                            low_id = max_present_id;
-                            *ids_to_check
-                                .get(
-                                    ids_to_check
-                                        .iter()
-                                        .position(|&id| id == max_present_id)
-                                        .unwrap()
-                                        + 1,
-                                )
-                                .unwrap_or(&high_id)
-                        },
-                    );
+    async fn get_latest_group_id(&self, max_gap: usize) -> RequestResult<Id> {
+        Ok(find_latest_id(&GroupIdProbe(self), max_gap).await?.id)
+    }
+
+    async fn get_latest_group_id_from(
+        &self,
+        hint: Id,
+        max_gap: usize,
+    ) -> RequestResult<LatestIdSearch> {
+        find_latest_id_from(&GroupIdProbe(self), hint, max_gap).await
+    }
+}
+impl<T: GroupsApi> GroupsApiExt for T {}
+
+/// Parses a UTC timestamp in the `2013-10-16T20:38:31.433Z` form the Roblox API uses.
+#[must_use]
+pub fn parse_timestamp(text: &str) -> Option<SystemTime> {
+    let (date, time) = text.strip_suffix('Z')?.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(str::parse::<u64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let mut time = time.splitn(3, ':').map(str::parse::<u64>);
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+    if year < 1970
+        || !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+        || fraction.len() > 9
+        || !fraction.bytes().all(|byte| byte.is_ascii_digit())
+    {
+        return None;
+    }
+    let nanos = fraction
+        .bytes()
+        .chain(std::iter::repeat(b'0'))
+        .take(9)
+        .fold(0, |nanos, digit| nanos * 10 + u32::from(digit - b'0'));
+
+    // Days since the epoch, counting years from March so that leap days come last
+    let (year, month) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let year_of_era = year % 400;
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = year / 400 * 146_097 + day_of_era - 719_468;
+    let seconds = days * 86_400 + hour * 3_600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::new(seconds, nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeSet,
+        num::NonZeroUsize,
+        sync::atomic::{AtomicUsize, Ordering},
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use async_trait::async_trait;
+    use proptest::prelude::*;
+    use roblox_api::apis::{Id, RequestResult};
+    use tokio::runtime;
+    use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
+
+    use super::{
+        constants, find_latest_id, find_latest_id_from, get_partitioning_ids,
+        get_present_ids_one_by_one, parse_timestamp, LatestIdProbe,
+    };
+
+    const MAX_GAP: usize = 500;
+
+    struct SyntheticIdSpace {
+        latest: u64,
+        holes: BTreeSet<u64>,
+        requests: AtomicUsize,
+    }
+
+    impl SyntheticIdSpace {
+        // Ids 1..=latest with runs of deleted ids no longer than `max_hole`, dense near the top
+        // and scattered below it
+        fn with_holes(latest: u64, max_hole: u64, seed: u64) -> Self {
+            let mut state = seed;
+            let mut next_random = move || {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                state >> 33
+            };
+            let mut holes = BTreeSet::new();
+            if max_hole > 0 {
+                let mut hole_end = latest - 1;
+                while hole_end > latest.saturating_sub(20_000) {
+                    let hole = next_random() % (max_hole + 1);
+                    holes.extend(hole_end.saturating_sub(hole).max(1)..hole_end);
+                    hole_end = hole_end.saturating_sub(hole + 1 + next_random() % 500);
                 }
-                Err(error) => {
-                    return Err(error);
+                for _ in 0..200 {
+                    let hole_end = 1 + next_random() % latest.saturating_sub(30_000).max(1);
+                    let hole_start = hole_end.saturating_sub(next_random() % (max_hole + 1));
+                    if holes
+                        .range(hole_start.saturating_sub(1)..=hole_end)
+                        .next()
+                        .is_none()
+                    {
+                        holes.extend(hole_start.max(1)..hole_end);
+                    }
                 }
             }
+            Self {
+                latest,
+                holes,
+                requests: AtomicUsize::new(0),
+            }
+        }
+
+        fn latest(&self) -> Id {
+            Id::new(self.latest).unwrap()
+        }
+    }
+
+    #[async_trait]
+    impl LatestIdProbe for SyntheticIdSpace {
+        type Error = roblox_api::apis::Error;
+
+        fn max_batch_size(&self) -> NonZeroUsize {
+            NonZeroUsize::new(constants::MAX_IDS_IN_BATCH_REQUEST).unwrap()
+        }
+
+        async fn get_present_ids(&self, ids: &[Id]) -> RequestResult<Vec<Id>> {
+            assert!(ids.len() <= constants::MAX_IDS_IN_BATCH_REQUEST);
+            self.requests.fetch_add(1, Ordering::Relaxed);
+            Ok(ids
+                .iter()
+                .copied()
+                .filter(|id| id.get() <= self.latest && !self.holes.contains(&id.get()))
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn finds_latest_id_without_holes() {
+        for latest in [1, 2, 99, 100, 101, 12_345, 16_000_000] {
+            let space = SyntheticIdSpace::with_holes(latest, 0, latest);
+            let search = find_latest_id(&space, MAX_GAP).await.unwrap();
+            assert_eq!(search.id, space.latest());
+        }
+    }
+
+    #[tokio::test]
+    async fn finds_latest_id_with_holes_at_the_frontier() {
+        for seed in 1..=50 {
+            let space = SyntheticIdSpace::with_holes(16_000_000 + seed * 7919, 400, seed);
+            let search = find_latest_id(&space, MAX_GAP).await.unwrap();
+            assert_eq!(search.id, space.latest(), "seed {seed}");
+            assert_eq!(search.requests, space.requests.load(Ordering::Relaxed));
+        }
+    }
+
+    #[tokio::test]
+    async fn finds_latest_id_from_any_hint() {
+        for seed in 1..=20 {
+            let space = SyntheticIdSpace::with_holes(16_000_000 + seed * 104_729, 400, seed);
+            let latest = space.latest().get();
+            for hint in [
+                1,
+                latest - 1_000_000,
+                latest - 150,
+                latest,
+                latest + 3,
+                latest * 2,
+            ] {
+                let search = find_latest_id_from(&space, Id::new(hint).unwrap(), MAX_GAP)
+                    .await
+                    .unwrap();
+                assert_eq!(search.id, space.latest(), "seed {seed}, hint {hint}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn hint_near_frontier_is_cheap() {
+        let space = SyntheticIdSpace::with_holes(16_000_000, 400, 42);
+        let latest = space.latest().get();
+        let search = find_latest_id_from(&space, Id::new(latest - 50).unwrap(), MAX_GAP)
+            .await
+            .unwrap();
+        assert_eq!(search.id, space.latest());
+        assert!(search.requests <= 20, "used {} requests", search.requests);
+    }
+
+    fn block_on<T>(future: impl std::future::Future<Output = T>) -> T {
+        runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    proptest! {
+        #[test]
+        fn partitioning_ids_are_increasing_and_evenly_spaced(
+            low_id in 1..u64::MAX / 2,
+            width in 2..1_000_000_000_000u64,
+            count in 1..=500usize,
+        ) {
+            let high_id = low_id + width;
+            let ids = get_partitioning_ids(
+                Id::new(low_id).unwrap(),
+                Id::new(high_id).unwrap(),
+                NonZeroUsize::new(count).unwrap(),
+            );
+            let search_space = width - 1;
+
+            prop_assert_eq!(ids.len() as u64, search_space.min(count as u64));
+            prop_assert!(ids.iter().all(|id| low_id < id.get() && id.get() < high_id));
+
+            let bounds = std::iter::once(low_id)
+                .chain(ids.iter().map(|id| id.get()))
+                .chain(std::iter::once(high_id))
+                .collect::<Vec<_>>();
+            let gaps = bounds.windows(2).map(|pair| pair[1] - pair[0]).collect::<Vec<_>>();
+            prop_assert!(gaps.iter().all(|&gap| gap > 0));
+            let (min_gap, max_gap) = (gaps.iter().min().unwrap(), gaps.iter().max().unwrap());
+            prop_assert!(max_gap - min_gap <= 1, "gaps range from {} to {}", min_gap, max_gap);
         }
-        Ok(low_id)
+
+        #[test]
+        fn partitioning_ids_cover_small_search_spaces(
+            low_id in 1..u64::MAX / 2,
+            width in 2..=101u64,
+        ) {
+            let ids = get_partitioning_ids(
+                Id::new(low_id).unwrap(),
+                Id::new(low_id + width).unwrap(),
+                NonZeroUsize::new(constants::MAX_IDS_IN_BATCH_REQUEST).unwrap(),
+            );
+            prop_assert!(ids.iter().map(|id| id.get()).eq(low_id + 1..low_id + width));
+        }
+
+        #[test]
+        #[should_panic(expected = "low id not lower than high id")]
+        fn partitioning_ids_reject_empty_ranges(low_id in 2..u64::MAX, below in 0..2u64) {
+            let high_id = low_id - below;
+            let _ = get_partitioning_ids(
+                Id::new(low_id).unwrap(),
+                Id::new(high_id).unwrap(),
+                NonZeroUsize::new(1).unwrap(),
+            );
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn search_converges_to_latest_id(
+            latest in 1..2_000_000_000u64,
+            max_hole in 0..MAX_GAP as u64,
+            seed: u64,
+        ) {
+            let space = SyntheticIdSpace::with_holes(latest, max_hole, seed);
+            let search = block_on(find_latest_id(&space, MAX_GAP)).unwrap();
+            prop_assert_eq!(search.id, space.latest());
+            prop_assert_eq!(search.requests, space.requests.load(Ordering::Relaxed));
+            prop_assert!(search.requests <= 80, "used {} requests", search.requests);
+        }
+
+        #[test]
+        fn search_from_hint_converges_to_latest_id(
+            latest in 1..2_000_000_000u64,
+            max_hole in 0..MAX_GAP as u64,
+            seed: u64,
+            hint in 1..4_000_000_000u64,
+        ) {
+            let space = SyntheticIdSpace::with_holes(latest, max_hole, seed);
+            let search =
+                block_on(find_latest_id_from(&space, Id::new(hint).unwrap(), MAX_GAP)).unwrap();
+            prop_assert_eq!(search.id, space.latest());
+            prop_assert!(search.requests <= 120, "used {} requests", search.requests);
+        }
+    }
+
+    #[tokio::test]
+    async fn one_by_one_lookups_skip_missing_ids() {
+        let server = MockServer::start().await;
+        for (id, status) in [(1, 200), (3, 400), (4, 200), (5, 429)] {
+            Mock::given(path(format!("/{id}")))
+                .respond_with(ResponseTemplate::new(status))
+                .mount(&server)
+                .await;
+        }
+        let http = reqwest::Client::new();
+        let url = |id| format!("{}/{id}", server.uri());
+        let ids: Vec<Id> = (1..=4).map(|id| Id::new(id).unwrap()).collect();
+
+        // Unmatched ids are not found
+        let mut present = get_present_ids_one_by_one(&http, &ids, url).await.unwrap();
+        present.sort_unstable();
+        assert_eq!(present, [ids[0], ids[3]]);
+        // Other failures, like rate limits, fail the whole batch
+        let limited = [Id::new(4).unwrap(), Id::new(5).unwrap()];
+        assert!(get_present_ids_one_by_one(&http, &limited, url)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn timestamps_parse_with_and_without_fractions() {
+        assert_eq!(
+            parse_timestamp("2013-10-16T20:38:31.433Z"),
+            Some(UNIX_EPOCH + Duration::new(1_381_955_911, 433_000_000))
+        );
+        assert_eq!(
+            parse_timestamp("2024-02-29T12:34:56Z"),
+            Some(UNIX_EPOCH + Duration::from_secs(1_709_210_096))
+        );
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00Z"), Some(UNIX_EPOCH));
+        assert_eq!(parse_timestamp("2024-13-01T00:00:00Z"), None);
+        assert_eq!(parse_timestamp("2024-02-29T00:00:00+01:00"), None);
     }
 }
-impl<T: GroupsApi> GroupsApiExt for T {}
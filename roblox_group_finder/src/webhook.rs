@@ -0,0 +1,412 @@
+use std::{sync::Mutex, time::Duration};
+
+use async_trait::async_trait;
+use reqwest::Client;
+use roblox_api::apis::Id;
+use serde::{Deserialize, Serialize};
+use tokio::time::{self, Instant};
+use tracing::warn;
+
+use crate::{
+    constants::{WEBHOOK_MIN_INTERVAL, WEBHOOK_RETRY_LIMIT, WEBHOOK_RETRY_WAIT, WEBHOOK_TIMEOUT},
+    events::{EventSink, FinderEvent},
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// A flat JSON object with the event name, group id, funds and a message.
+    #[default]
+    Json,
+    /// A message with one embed, for Discord webhook URLs.
+    Discord,
+}
+
+// A `[[webhooks]]` entry
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: String,
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// Minimum time between two requests to this webhook, in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_interval: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_limit: Option<usize>,
+}
+
+/// Posts claims, leaves, prunes, lost races, failed leaves and failures that stop the finder to
+/// a webhook.
+#[derive(Debug)]
+pub struct WebhookSink {
+    client: Client,
+    url: String,
+    format: WebhookFormat,
+    min_interval: Duration,
+    retry_limit: usize,
+    next_request: Mutex<Instant>,
+}
+
+impl WebhookSink {
+    #[must_use]
+    pub fn new(config: WebhookConfig) -> Self {
+        Self {
+            client: Client::new(),
+            url: config.url,
+            format: config.format,
+            min_interval: Duration::from_millis(
+                config.min_interval.unwrap_or(WEBHOOK_MIN_INTERVAL),
+            ),
+            retry_limit: config.retry_limit.unwrap_or(WEBHOOK_RETRY_LIMIT),
+            next_request: Mutex::new(Instant::now()),
+        }
+    }
+
+    async fn wait_for_rate_limit(&self) {
+        let request_at = {
+            let mut next_request = self.next_request.lock().unwrap();
+            let request_at = (*next_request).max(Instant::now());
+            *next_request = request_at + self.min_interval;
+            request_at
+        };
+        time::sleep_until(request_at).await;
+    }
+
+    async fn post(&self, notification: &Notification) -> reqwest::Result<()> {
+        let request = self.client.post(&self.url).timeout(WEBHOOK_TIMEOUT);
+        match self.format {
+            WebhookFormat::Json => request.json(&notification.json()),
+            WebhookFormat::Discord => request.json(&notification.discord()),
+        }
+        .send()
+        .await?
+        .error_for_status()
+        .map(drop)
+    }
+}
+
+#[async_trait]
+impl EventSink for WebhookSink {
+    async fn handle(&self, event: &FinderEvent) {
+        let Some(notification) = Notification::from_event(event) else {
+            return;
+        };
+        let mut retry_wait = WEBHOOK_RETRY_WAIT;
+        for attempt in 0..=self.retry_limit {
+            self.wait_for_rate_limit().await;
+            match self.post(&notification).await {
+                Ok(()) => return,
+                Err(error) if attempt < self.retry_limit => {
+                    warn!(
+                        "Failed to send {} webhook, retrying in {:?}, error: {}",
+                        notification.event, retry_wait, error
+                    );
+                    time::sleep(retry_wait).await;
+                    retry_wait *= 2;
+                }
+                Err(error) => warn!(
+                    "Failed to send {} webhook, giving up, error: {}",
+                    notification.event, error
+                ),
+            }
+        }
+    }
+}
+
+struct Notification {
+    event: &'static str,
+    group_id: Option<Id>,
+    funds: Option<u64>,
+    title: String,
+    message: String,
+    color: u32,
+}
+
+impl Notification {
+    fn from_event(event: &FinderEvent) -> Option<Self> {
+        let notification = match event {
+            FinderEvent::Claimed { id, funds } => Self {
+                event: "claimed",
+                group_id: Some(*id),
+                funds: Some(*funds),
+                title: format!("Claimed group {id}"),
+                message: format!("Kept with {funds} robux"),
+                color: 0x57F287,
+            },
+            FinderEvent::FundsUnknown { id } => Self {
+                event: "claimed",
+                group_id: Some(*id),
+                funds: None,
+                title: format!("Claimed group {id}"),
+                message: "Kept, its funds couldn't be fetched".to_owned(),
+                color: 0x57F287,
+            },
+            FinderEvent::Left { id, funds } => Self {
+                event: "left",
+                group_id: Some(*id),
+                funds: Some(*funds),
+                title: format!("Left group {id}"),
+                message: format!("Rejected by the claim policy with {funds} robux"),
+                color: 0xFEE75C,
+            },
+            FinderEvent::Pruned { id, funds } => Self {
+                event: "pruned",
+                group_id: Some(*id),
+                funds: Some(*funds),
+                title: format!("Pruned group {id}"),
+                message: format!("Left with {funds} robux to free a slot"),
+                color: 0xFEE75C,
+            },
+            FinderEvent::ClaimLost { id, owner } => Self {
+                event: "claim_lost",
+                group_id: Some(*id),
+                funds: None,
+                title: format!("Lost group {id}"),
+                message: owner.map_or_else(
+                    || "Another account claimed it first".to_owned(),
+                    |owner| format!("User {owner} claimed it first"),
+                ),
+                color: 0xED4245,
+            },
+            FinderEvent::LeaveFailed { id } => Self {
+                event: "leave_failed",
+                group_id: Some(*id),
+                funds: None,
+                title: format!("Failed to leave group {id}"),
+                message: "The group still takes up one of the account's slots".to_owned(),
+                color: 0xED4245,
+            },
+            FinderEvent::GroupLimitReached => Self {
+                event: "group_limit_reached",
+                group_id: None,
+                funds: None,
+                title: "Group limit reached".to_owned(),
+                message: "The account can't claim any more groups, stopping".to_owned(),
+                color: 0x5865F2,
+            },
+            FinderEvent::CheckTasksExhausted => Self {
+                event: "check_tasks_exhausted",
+                group_id: None,
+                funds: None,
+                title: "Check tasks exhausted".to_owned(),
+                message: "Every batch check task stopped after repeated errors, check the proxies"
+                    .to_owned(),
+                color: 0xED4245,
+            },
+            FinderEvent::Fatal { message } => Self {
+                event: "fatal",
+                group_id: None,
+                funds: None,
+                title: "Finder stopped".to_owned(),
+                message: message.clone(),
+                color: 0xED4245,
+            },
+            FinderEvent::Detected { .. }
+            | FinderEvent::ClaimFailed { .. }
+            | FinderEvent::LeftUnclaimed { .. }
+            | FinderEvent::Stopped => return None,
+        };
+        Some(notification)
+    }
+
+    fn json(&self) -> JsonPayload<'_> {
+        JsonPayload {
+            event: self.event,
+            group_id: self.group_id.map(Id::get),
+            funds: self.funds,
+            message: &self.message,
+        }
+    }
+
+    fn discord(&self) -> DiscordPayload<'_> {
+        DiscordPayload {
+            embeds: [DiscordEmbed {
+                title: &self.title,
+                description: &self.message,
+                color: self.color,
+                url: self
+                    .group_id
+                    .map(|id| format!("https://www.roblox.com/groups/{id}")),
+            }],
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonPayload<'a> {
+    event: &'static str,
+    group_id: Option<u64>,
+    funds: Option<u64>,
+    message: &'a str,
+}
+
+#[derive(Serialize)]
+struct DiscordPayload<'a> {
+    embeds: [DiscordEmbed<'a>; 1],
+}
+
+#[derive(Serialize)]
+struct DiscordEmbed<'a> {
+    title: &'a str,
+    description: &'a str,
+    color: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use tokio::time::Instant;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::{WebhookConfig, WebhookFormat, WebhookSink};
+    use crate::{
+        events::{EventSink, FinderEvent},
+        fake::id,
+    };
+
+    fn sink(server: &MockServer, format: WebhookFormat) -> WebhookSink {
+        WebhookSink::new(WebhookConfig {
+            url: format!("{}/hook", server.uri()),
+            format,
+            min_interval: Some(100),
+            retry_limit: Some(1),
+        })
+    }
+
+    async fn bodies(server: &MockServer) -> Vec<Value> {
+        server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .map(|request| request.body_json().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn posts_discord_embeds_for_claims_only() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&server)
+            .await;
+        let sink = sink(&server, WebhookFormat::Discord);
+
+        sink.handle(&FinderEvent::Detected { id: id(5) }).await;
+        sink.handle(&FinderEvent::Claimed {
+            id: id(5),
+            funds: 20,
+        })
+        .await;
+
+        assert_eq!(
+            bodies(&server).await,
+            [json!({
+                "embeds": [{
+                    "title": "Claimed group 5",
+                    "description": "Kept with 20 robux",
+                    "color": 0x57F287,
+                    "url": "https://www.roblox.com/groups/5",
+                }]
+            })]
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_failed_requests_at_the_rate_cap() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        let sink = sink(&server, WebhookFormat::Json);
+
+        let start = Instant::now();
+        sink.handle(&FinderEvent::Fatal {
+            message: "Browser ID is invalid".to_owned(),
+        })
+        .await;
+        sink.handle(&FinderEvent::GroupLimitReached).await;
+
+        let fatal = json!({
+            "event": "fatal",
+            "group_id": null,
+            "funds": null,
+            "message": "Browser ID is invalid",
+        });
+        let bodies = bodies(&server).await;
+        assert_eq!(bodies[..2], [fatal.clone(), fatal]);
+        assert_eq!(bodies[2]["event"], "group_limit_reached");
+        assert!(start.elapsed().as_millis() >= 200);
+    }
+
+    #[tokio::test]
+    async fn posts_lost_races_and_groups_that_hold_slots() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        let sink = sink(&server, WebhookFormat::Json);
+
+        sink.handle(&FinderEvent::ClaimLost {
+            id: id(7),
+            owner: Some(id(9)),
+        })
+        .await;
+        sink.handle(&FinderEvent::LeaveFailed { id: id(7) }).await;
+        sink.handle(&FinderEvent::Pruned {
+            id: id(8),
+            funds: 3,
+        })
+        .await;
+
+        let bodies = bodies(&server).await;
+        assert_eq!(
+            bodies[0],
+            json!({
+                "event": "claim_lost",
+                "group_id": 7,
+                "funds": null,
+                "message": "User 9 claimed it first",
+            })
+        );
+        assert_eq!(bodies[1]["event"], "leave_failed");
+        assert_eq!(bodies[2]["event"], "pruned");
+        assert_eq!(bodies[2]["funds"], 3);
+    }
+
+    #[tokio::test]
+    async fn posts_claims_with_unknown_funds() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        let sink = sink(&server, WebhookFormat::Json);
+
+        sink.handle(&FinderEvent::FundsUnknown { id: id(6) }).await;
+
+        assert_eq!(
+            bodies(&server).await,
+            [json!({
+                "event": "claimed",
+                "group_id": 6,
+                "funds": null,
+                "message": "Kept, its funds couldn't be fetched",
+            })]
+        );
+    }
+}